@@ -0,0 +1,107 @@
+//! Running the keyboard view without a terminal: an adapter drawing into
+//! memory and a port feeding the application scripted events.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use tuirealm::listener::{ListenerResult, Poll};
+use tuirealm::ratatui::backend::TestBackend;
+use tuirealm::ratatui::buffer::Buffer;
+use tuirealm::ratatui::{CompletedFrame, Frame, Terminal};
+use tuirealm::terminal::{TerminalAdapter, TerminalError, TerminalResult};
+use tuirealm::Event;
+
+use crate::UserEvent;
+
+/// Draws into a [`TestBackend`] of a fixed size and keeps the last frame.
+pub struct TestTerminalAdapter {
+	terminal: Terminal<TestBackend>,
+	screen: Rc<RefCell<Buffer>>,
+}
+
+impl TestTerminalAdapter {
+	pub fn new(width: u16, height: u16) -> Self {
+		Self {
+			terminal: Terminal::new(TestBackend::new(width, height))
+				.expect("the test backend cannot fail"),
+			screen: Rc::new(RefCell::new(Buffer::empty(Default::default()))),
+		}
+	}
+
+	/// The last frame drawn, still readable once the adapter has been
+	/// handed to a `TerminalBridge`.
+	pub fn screen(&self) -> Rc<RefCell<Buffer>> {
+		Rc::clone(&self.screen)
+	}
+}
+
+impl TerminalAdapter for TestTerminalAdapter {
+	fn draw<F>(&mut self, render_callback: F) -> TerminalResult<CompletedFrame<'_>>
+	where
+		F: FnOnce(&mut Frame<'_>),
+	{
+		let frame = self
+			.terminal
+			.draw(render_callback)
+			.map_err(|_| TerminalError::CannotDrawFrame)?;
+		*self.screen.borrow_mut() = frame.buffer.clone();
+		Ok(frame)
+	}
+
+	fn clear_screen(&mut self) -> TerminalResult<()> {
+		self
+			.terminal
+			.clear()
+			.map_err(|_| TerminalError::CannotClear)
+	}
+
+	fn enable_raw_mode(&mut self) -> TerminalResult<()> {
+		Ok(())
+	}
+
+	fn disable_raw_mode(&mut self) -> TerminalResult<()> {
+		Ok(())
+	}
+
+	fn enter_alternate_screen(&mut self) -> TerminalResult<()> {
+		Ok(())
+	}
+
+	fn leave_alternate_screen(&mut self) -> TerminalResult<()> {
+		Ok(())
+	}
+
+	fn enable_mouse_capture(&mut self) -> TerminalResult<()> {
+		Ok(())
+	}
+
+	fn disable_mouse_capture(&mut self) -> TerminalResult<()> {
+		Ok(())
+	}
+}
+
+/// Events queued for the application, handed out by [`Script::port`] in
+/// order. Clones share the queue.
+#[derive(Clone, Default)]
+pub struct Script {
+	queue: Arc<Mutex<VecDeque<Event<UserEvent>>>>,
+}
+
+impl Script {
+	pub fn push(&self, event: Event<UserEvent>) {
+		self.queue.lock().expect("queue lock").push_back(event);
+	}
+
+	/// A port for the application's event listener reading this script.
+	pub fn port(&self) -> Box<dyn Poll<UserEvent>> {
+		Box::new(self.clone())
+	}
+}
+
+impl Poll<UserEvent> for Script {
+	fn poll(&mut self) -> ListenerResult<Option<Event<UserEvent>>> {
+		Ok(self.queue.lock().expect("queue lock").pop_front())
+	}
+}
@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use evdev::Device;
+use tuirealm::listener::{ListenerError, ListenerResult, Poll};
+use tuirealm::Event;
+
+use crate::recording::Playback;
+use crate::remap::status::{is_trusted, parse_line};
+use crate::remap::{KeyEvent, KeyState, VirtualKeyboard};
+use crate::UserEvent;
+
+/// Feeds the key events of an evdev device into the application. The
+/// device is only read, never grabbed, so typing still reaches the terminal.
+pub struct EvdevPort {
+	device: Device,
+	pending: VecDeque<UserEvent>,
+}
+
+impl EvdevPort {
+	pub fn open(path: &Path) -> Result<Self> {
+		let device = Device::open(path).with_context(|| {
+			format!(
+				"Cannot open {} (is this user in the `input` group?)",
+				path.display()
+			)
+		})?;
+		device
+			.set_nonblocking(true)
+			.with_context(|| format!("Cannot read {} without blocking", path.display()))?;
+		Ok(Self {
+			device,
+			pending: VecDeque::new(),
+		})
+	}
+}
+
+impl Poll<UserEvent> for EvdevPort {
+	fn poll(&mut self) -> ListenerResult<Option<Event<UserEvent>>> {
+		if self.pending.is_empty() {
+			match self.device.fetch_events() {
+				Ok(events) => self.pending.extend(
+					events
+						.filter_map(|event| KeyEvent::from_input(&event))
+						.filter_map(user_event),
+				),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+				Err(_) => return Err(ListenerError::PollFailed),
+			}
+		}
+		Ok(self.pending.pop_front().map(Event::User))
+	}
+}
+
+/// What the keyboard view makes of a key event; repeats change nothing.
+fn user_event(event: KeyEvent) -> Option<UserEvent> {
+	match event.state {
+		KeyState::Pressed => Some(UserEvent::KeyDown(event.code)),
+		KeyState::Released => Some(UserEvent::KeyUp(event.code)),
+		KeyState::Repeat => None,
+	}
+}
+
+/// Plays recorded key events into the application as the clock reaches
+/// them, writing them to a virtual keyboard as well when given one.
+pub struct ReplayPort {
+	playback: Playback,
+	output: Option<VirtualKeyboard>,
+	pending: VecDeque<UserEvent>,
+}
+
+impl ReplayPort {
+	pub fn new(playback: Playback, output: Option<VirtualKeyboard>) -> Self {
+		Self {
+			playback,
+			output,
+			pending: VecDeque::new(),
+		}
+	}
+}
+
+impl Poll<UserEvent> for ReplayPort {
+	fn poll(&mut self) -> ListenerResult<Option<Event<UserEvent>>> {
+		if self.pending.is_empty() {
+			let due = self.playback.due(Instant::now());
+			if let (Some(output), Some(last)) = (self.output.as_mut(), due.last()) {
+				output
+					.emit(due, last.time)
+					.map_err(|_| ListenerError::PollFailed)?;
+			}
+			self
+				.pending
+				.extend(due.iter().copied().filter_map(user_event));
+		}
+		Ok(self.pending.pop_front().map(Event::User))
+	}
+}
+
+/// How often to look for a daemon to follow when none is running.
+const DAEMON_RETRY: Duration = Duration::from_secs(1);
+
+/// Follows the layer the remapping daemon has active through its status
+/// socket, reconnecting whenever it is restarted. Sockets made by users
+/// other than this one and root are ignored.
+pub struct DaemonPort {
+	/// Where to look for the socket, in order.
+	paths: Vec<PathBuf>,
+	stream: Option<UnixStream>,
+	last_attempt: Option<Instant>,
+	/// Bytes read past the last complete line.
+	partial: Vec<u8>,
+	pending: VecDeque<UserEvent>,
+}
+
+impl DaemonPort {
+	pub fn new(paths: Vec<PathBuf>) -> Self {
+		Self {
+			paths,
+			stream: None,
+			last_attempt: None,
+			partial: Vec::new(),
+			pending: VecDeque::new(),
+		}
+	}
+
+	fn connect(&mut self) {
+		if self
+			.last_attempt
+			.is_some_and(|at| at.elapsed() < DAEMON_RETRY)
+		{
+			return;
+		}
+		self.last_attempt = Some(Instant::now());
+		let stream = self
+			.paths
+			.iter()
+			.filter(|path| is_trusted(path))
+			.find_map(|path| UnixStream::connect(path).ok());
+		if let Some(stream) = stream.filter(|stream| stream.set_nonblocking(true).is_ok()) {
+			self.partial.clear();
+			self.stream = Some(stream);
+		}
+	}
+
+	fn read(&mut self) {
+		let Some(stream) = self.stream.as_mut() else {
+			return;
+		};
+		let mut buffer = [0u8; 256];
+		loop {
+			match stream.read(&mut buffer) {
+				Ok(0) => break,
+				Ok(read) => self.partial.extend_from_slice(&buffer[..read]),
+				Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+					self.take_lines();
+					return;
+				}
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+				Err(_) => break,
+			}
+		}
+		// The daemon went away
+		self.take_lines();
+		self.stream = None;
+		self.pending.push_back(UserEvent::DaemonLayer(None));
+	}
+
+	fn take_lines(&mut self) {
+		while let Some(end) = self.partial.iter().position(|&byte| byte == b'\n') {
+			let line: Vec<u8> = self.partial.drain(..=end).collect();
+			if let Some(layer) = parse_line(&String::from_utf8_lossy(&line)) {
+				self
+					.pending
+					.push_back(UserEvent::DaemonLayer(Some(layer.to_string())));
+			}
+		}
+	}
+}
+
+impl Poll<UserEvent> for DaemonPort {
+	fn poll(&mut self) -> ListenerResult<Option<Event<UserEvent>>> {
+		if self.pending.is_empty() {
+			if self.stream.is_none() {
+				self.connect();
+			}
+			self.read();
+		}
+		Ok(self.pending.pop_front().map(Event::User))
+	}
+}
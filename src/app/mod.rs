@@ -1,2 +1,7 @@
 pub use super::*;
+#[cfg(test)]
+mod headless;
+pub mod input;
 pub mod model;
+#[cfg(test)]
+mod snapshot;
@@ -1,31 +1,132 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
 
-use tuirealm::event::NoUserEvent;
-use tuirealm::ratatui::layout::{Constraint, Direction, Layout};
+use anyhow::Context;
+use tuirealm::application::PollStrategy;
+use tuirealm::listener::Poll;
+use tuirealm::props::{Color, TextSpan};
+use tuirealm::ratatui::layout::{Constraint, Direction, Layout, Rect};
 use tuirealm::terminal::{CrosstermTerminalAdapter, TerminalAdapter, TerminalBridge};
-use tuirealm::{Application, AttrValue, Attribute, EventListenerCfg, Update};
+use tuirealm::{
+	Application, AttrValue, Attribute, EventListenerCfg, Sub, SubClause, SubEventClause, Update,
+};
 
-use super::{Id, Msg};
-use crate::components::Keyboard;
+use super::input::DaemonPort;
+use super::{Id, Msg, UserEvent};
+use crate::components::{
+	heatmap_value, layer_value, legends_value, KeyPicker, Keyboard, LayerPanel, SavePreview,
+	StatusBar, HEATMAP, LEGENDS, LIVE, TRANSPARENT,
+};
+use crate::config::edit::ConfigEditor;
+use crate::config::BASE_LAYER;
+use crate::keys;
+use crate::layout::Layout as PhysicalLayout;
+use crate::remap::{status, Keymap, LayerId, Target};
+use crate::stats::{Stats, StatsLock};
+
+const VIEW_HELP: &str = "F2 heatmap · Esc quit";
+const EDIT_HELP: &str =
+	"←↑↓→ move · Enter remap · Del unmap · Tab layer · s save · F2 heatmap · Esc quit";
 
 pub struct Model<T>
 where
 	T: TerminalAdapter,
 {
-	pub app: Application<Id, Msg, NoUserEvent>,
+	pub app: Application<Id, Msg, UserEvent>,
 	pub quit: bool,
 	pub redraw: bool,
 	pub terminal: TerminalBridge<T>,
+	/// The config being edited, `None` for a read-only view.
+	editor: Option<ConfigEditor>,
+	/// Set by Esc with unsaved edits; a second Esc quits anyway.
+	confirm_quit: bool,
+	/// Names of the edited config's layers, the base layer first.
+	layers: Vec<String>,
+	/// The layer drawn on the keyboard.
+	layer: LayerId,
+	/// Where press counts are kept, `None` to count this session only.
+	stats_path: Option<PathBuf>,
+	/// Presses from the stats file and this session.
+	presses: Stats,
+	/// Presses of this session not yet added to the stats file.
+	unsaved_presses: Stats,
+	heatmap: bool,
+	/// Set by the first F3; a second one resets the press counts.
+	confirm_reset: bool,
 }
 
 impl Default for Model<CrosstermTerminalAdapter> {
 	fn default() -> Self {
-		Self {
-			app: Self::init_app(),
-			quit: false,
-			redraw: true,
-			terminal: TerminalBridge::init_crossterm().expect("Cannot initialize terminal"),
+		Self::new(None, &PhysicalLayout::default(), None, None)
+	}
+}
+
+impl Model<CrosstermTerminalAdapter> {
+	/// A model drawing `layout` that lights keys from `input` when given,
+	/// such as an [`EvdevPort`](super::input::EvdevPort), from terminal
+	/// key events otherwise. With an `editor` the keycaps show its mappings
+	/// and can be remapped. Presses of `input` are added to the counts in
+	/// `stats` for the heatmap.
+	pub fn new(
+		input: Option<Box<dyn Poll<UserEvent>>>,
+		layout: &PhysicalLayout,
+		editor: Option<ConfigEditor>,
+		stats: Option<PathBuf>,
+	) -> Self {
+		let listener = Self::listener(input);
+		Self::with_terminal(
+			TerminalBridge::init_crossterm().expect("Cannot initialize terminal"),
+			listener,
+			layout,
+			editor,
+			stats,
+		)
+	}
+
+	/// Terminal key events, presses of `input` and the daemon's layer.
+	fn listener(input: Option<Box<dyn Poll<UserEvent>>>) -> EventListenerCfg<UserEvent> {
+		let mut listener = EventListenerCfg::default()
+			.crossterm_input_listener(Duration::from_millis(25), 3)
+			.poll_timeout(Duration::from_millis(10))
+			.tick_interval(Duration::from_millis(50));
+		if let Some(input) = input {
+			listener = listener.add_port(input, Duration::from_millis(5), 16);
+		}
+		// Follow the layer the remapping daemon has active, if it runs
+		listener.add_port(
+			Box::new(DaemonPort::new(status::socket_paths())),
+			Duration::from_millis(50),
+			4,
+		)
+	}
+
+	/// Takes over the terminal and runs until the view is closed.
+	pub fn run(&mut self) {
+		let _ = self.terminal.enter_alternate_screen();
+		let _ = self.terminal.enable_raw_mode();
+
+		while !self.quit {
+			match self.app.tick(PollStrategy::Once) {
+				Err(err) => {
+					println!("Application error: {}", err);
+				}
+				Ok(messages) if !messages.is_empty() => {
+					self.redraw = true;
+					self.process(messages);
+				}
+				_ => {}
+			}
+
+			if self.redraw {
+				self.view();
+				self.redraw = false;
+			}
 		}
+
+		let _ = self.terminal.leave_alternate_screen();
+		let _ = self.terminal.disable_raw_mode();
+		let _ = self.terminal.clear_screen();
 	}
 }
 
@@ -33,36 +134,325 @@ impl<T> Model<T>
 where
 	T: TerminalAdapter,
 {
+	/// Like [`Model::new`], drawing to any `terminal` and taking events
+	/// from `listener`, such as a headless terminal fed a script.
+	pub fn with_terminal(
+		terminal: TerminalBridge<T>,
+		listener: EventListenerCfg<UserEvent>,
+		layout: &PhysicalLayout,
+		editor: Option<ConfigEditor>,
+		stats: Option<PathBuf>,
+	) -> Self {
+		let mut model = Self {
+			app: Self::init_app(listener, layout, editor.is_some()),
+			quit: false,
+			redraw: true,
+			terminal,
+			editor,
+			confirm_quit: false,
+			layers: Vec::new(),
+			layer: 0,
+			stats_path: stats,
+			presses: Stats::default(),
+			unsaved_presses: Stats::default(),
+			heatmap: false,
+			confirm_reset: false,
+		};
+		model.show_layers();
+		model.show_mappings();
+		model.load_stats();
+		model
+	}
+
+	/// Runs each message through [`Update`], along with the messages it
+	/// leads to.
+	pub fn process(&mut self, messages: Vec<Msg>) {
+		for msg in messages {
+			let mut msg = Some(msg);
+			while msg.is_some() {
+				msg = self.update(msg);
+			}
+		}
+	}
+
 	pub fn view(&mut self) {
+		let layer_panel = !self.layers.is_empty() || self.daemon_layer().is_some();
 		assert!(self
 			.terminal
 			.draw(|f| {
 				let chunks = Layout::default()
 					.direction(Direction::Vertical)
 					.margin(1)
-					.constraints([Constraint::Length(18)].as_ref())
+					.constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
 					.split(f.area());
 
-				self.app.view(&Id::Keyboard, f, chunks[0]);
+				if layer_panel {
+					let columns = Layout::default()
+						.direction(Direction::Horizontal)
+						.constraints([Constraint::Min(0), Constraint::Length(18)].as_ref())
+						.split(chunks[0]);
+					self.app.view(&Id::Keyboard, f, columns[0]);
+					self.app.view(&Id::LayerPanel, f, columns[1]);
+				} else {
+					self.app.view(&Id::Keyboard, f, chunks[0]);
+				}
+				self.app.view(&Id::StatusBar, f, chunks[1]);
+
+				if self.app.mounted(&Id::KeyPicker) {
+					self.app.view(&Id::KeyPicker, f, popup(f.area(), 44, 20));
+				}
+				if self.app.mounted(&Id::SavePreview) {
+					let area = f.area();
+					self.app.view(
+						&Id::SavePreview,
+						f,
+						popup(area, area.width * 4 / 5, area.height * 4 / 5),
+					);
+				}
 			})
 			.is_ok());
 	}
 
-	fn init_app() -> Application<Id, Msg, NoUserEvent> {
-		let mut app: Application<Id, Msg, NoUserEvent> = Application::init(
-			EventListenerCfg::default()
-				.crossterm_input_listener(Duration::from_millis(25), 3)
-				.poll_timeout(Duration::from_millis(10))
-				.tick_interval(Duration::from_secs(1)),
-		);
+	fn init_app(
+		listener: EventListenerCfg<UserEvent>,
+		layout: &PhysicalLayout,
+		editable: bool,
+	) -> Application<Id, Msg, UserEvent> {
+		let mut app: Application<Id, Msg, UserEvent> = Application::init(listener);
 
+		let mut keyboard = Keyboard::with_custom_layout(layout);
+		if editable {
+			keyboard = keyboard.editable();
+		}
+		// Key events and ticks keep reaching the keyboard behind popups
+		assert!(app
+			.mount(
+				Id::Keyboard,
+				Box::new(keyboard),
+				vec![Sub::new(SubEventClause::Any, SubClause::Always)],
+			)
+			.is_ok());
+		let help = if editable { EDIT_HELP } else { VIEW_HELP };
 		assert!(app
-			.mount(Id::Keyboard, Box::new(Keyboard::new()), Vec::new(),)
+			.mount(
+				Id::StatusBar,
+				Box::new(StatusBar::default().text(help)),
+				Vec::new()
+			)
 			.is_ok());
 
 		assert!(app.active(&Id::Keyboard).is_ok());
 		app
 	}
+
+	fn set_status<S: Into<String>>(&mut self, text: S) {
+		assert!(self
+			.app
+			.attr(
+				&Id::StatusBar,
+				Attribute::Text,
+				AttrValue::String(text.into())
+			)
+			.is_ok());
+	}
+
+	/// The edited config resolved to key codes.
+	fn keymap(&self) -> Option<anyhow::Result<Keymap>> {
+		self.editor.as_ref().map(|editor| {
+			editor
+				.config()
+				.and_then(|config| Keymap::from_config(&config))
+		})
+	}
+
+	/// Lists the edited config's layers beside the keyboard. The panel is
+	/// there without a config too, showing the daemon's layer alone.
+	fn show_layers(&mut self) {
+		if let Some(Ok(keymap)) = self.keymap() {
+			self.layers = (0..keymap.layer_count())
+				.map(|layer| keymap.layer_name(layer).to_string())
+				.collect();
+		}
+		// Hears from the daemon whatever has focus
+		assert!(self
+			.app
+			.mount(
+				Id::LayerPanel,
+				Box::new(LayerPanel::new(self.layers.clone())),
+				vec![Sub::new(SubEventClause::Any, SubClause::Always)],
+			)
+			.is_ok());
+	}
+
+	/// The layer the running daemon has active, `None` when it is not
+	/// running.
+	fn daemon_layer(&self) -> Option<String> {
+		match self.app.query(&Id::LayerPanel, LIVE) {
+			Ok(Some(AttrValue::String(layer))) => Some(layer),
+			_ => None,
+		}
+	}
+
+	/// Draws the shown layer of the edited config on the keycaps.
+	fn show_mappings(&mut self) {
+		let legends = match self.keymap() {
+			Some(Ok(keymap)) => keymap_legends(&keymap, self.layer),
+			Some(Err(err)) => {
+				self.set_status(format!("{:#}", err));
+				return;
+			}
+			None => return,
+		};
+		assert!(self
+			.app
+			.attr(&Id::Keyboard, LEGENDS, legends_value(legends))
+			.is_ok());
+		// Keys the base layer leaves alone just type themselves
+		assert!(self
+			.app
+			.attr(&Id::Keyboard, TRANSPARENT, AttrValue::Flag(self.layer != 0))
+			.is_ok());
+	}
+
+	fn load_stats(&mut self) {
+		let Some(path) = &self.stats_path else {
+			return;
+		};
+		match Stats::load(path) {
+			Ok(stats) => self.presses = stats,
+			Err(err) => {
+				self.set_status(format!("{:#}, counting this session only", err));
+				self.stats_path = None;
+			}
+		}
+	}
+
+	/// Adds this session's presses to the stats file, unless `start` is
+	/// counting into it and so has them already.
+	pub fn save_stats(&mut self) -> anyhow::Result<()> {
+		let unsaved = std::mem::take(&mut self.unsaved_presses);
+		match &self.stats_path {
+			Some(path) if !unsaved.is_empty() => StatsLock::wait(path)
+				.and_then(|file| file.add(&unsaved))
+				.context("The view's presses were not added"),
+			_ => Ok(()),
+		}
+	}
+
+	/// Colours the keycaps by press count, or clears them.
+	fn show_heatmap(&mut self) {
+		let value = if self.heatmap {
+			heatmap_value(&self.presses.keys)
+		} else {
+			AttrValue::Flag(false)
+		};
+		assert!(self.app.attr(&Id::Keyboard, HEATMAP, value).is_ok());
+	}
+
+	/// Forgets every press counted, in the file too.
+	fn reset_stats(&mut self) -> anyhow::Result<()> {
+		if let Some(path) = &self.stats_path {
+			StatsLock::wait(path)?.reset()?;
+		}
+		self.presses = Stats::default();
+		self.unsaved_presses = Stats::default();
+		self.show_heatmap();
+		Ok(())
+	}
+
+	fn help(&self) -> &'static str {
+		if self.editor.is_some() {
+			EDIT_HELP
+		} else {
+			VIEW_HELP
+		}
+	}
+
+	/// Switches the keyboard to `layer`, ignoring layers the config lacks.
+	fn show_layer(&mut self, layer: LayerId) {
+		if layer >= self.layers.len() {
+			return;
+		}
+		self.layer = layer;
+		assert!(self
+			.app
+			.attr(&Id::LayerPanel, Attribute::Value, layer_value(layer))
+			.is_ok());
+		self.show_mappings();
+	}
+
+	/// Name of the layer shown, which edits go to.
+	fn shown_layer(&self) -> String {
+		self
+			.layers
+			.get(self.layer)
+			.cloned()
+			.unwrap_or_else(|| BASE_LAYER.to_string())
+	}
+
+	/// Applies an edit and reports how it went in the status bar.
+	fn edit<F>(&mut self, change: F)
+	where
+		F: FnOnce(&mut ConfigEditor) -> anyhow::Result<String>,
+	{
+		let Some(editor) = self.editor.as_mut() else {
+			return;
+		};
+		match change(editor) {
+			Ok(done) => {
+				self.set_status(format!("{}, s to save", done));
+				self.show_mappings();
+			}
+			Err(err) => self.set_status(format!("{:#}", err)),
+		}
+	}
+
+	fn close_popup(&mut self, id: Id) {
+		let _ = self.app.umount(&id);
+	}
+}
+
+/// Keycap legends for what `layer` binds: keys in yellow, layer switches
+/// by layer name in blue and macros in green, tap before hold.
+fn keymap_legends(keymap: &Keymap, layer: LayerId) -> HashMap<String, Vec<TextSpan>> {
+	keymap
+		.legends(layer)
+		.into_iter()
+		.map(|(key, targets)| {
+			let spans = targets
+				.into_iter()
+				.map(|target| match target {
+					Target::Key(_) => TextSpan::new(keymap.describe(target)).fg(Color::LightYellow),
+					Target::Layer(switch) => {
+						TextSpan::new(keymap.layer_name(switch.layer)).fg(Color::LightBlue)
+					}
+					Target::Macro(_) => TextSpan::new(keymap.describe(target)).fg(Color::LightGreen),
+				})
+				.collect();
+			(key, spans)
+		})
+		.collect()
+}
+
+/// Where an edit went, said only for layers other than the base one.
+fn on(layer: &str) -> String {
+	if layer == BASE_LAYER {
+		String::new()
+	} else {
+		format!(" on {}", layer)
+	}
+}
+
+/// A `width` by `height` rectangle centred in `area`.
+fn popup(area: Rect, width: u16, height: u16) -> Rect {
+	let width = width.min(area.width);
+	let height = height.min(area.height);
+	Rect::new(
+		area.x + (area.width - width) / 2,
+		area.y + (area.height - height) / 2,
+		width,
+		height,
+	)
 }
 
 impl<T> Update<Msg> for Model<T>
@@ -74,26 +464,140 @@ where
 			// Always set redraw flag to true when a message is received
 			self.redraw = true;
 
+			// Anything done after a refused quit asks again next time
+			if !matches!(msg, Msg::AppClose | Msg::Redraw | Msg::KeyPressed(_)) {
+				self.confirm_quit = false;
+			}
+			if !matches!(msg, Msg::ResetStats | Msg::Redraw | Msg::KeyPressed(_)) {
+				self.confirm_reset = false;
+			}
+
 			match msg {
 				Msg::AppClose => {
-					self.quit = true;
+					let unsaved = self.editor.as_ref().is_some_and(ConfigEditor::is_modified);
+					if unsaved && !self.confirm_quit {
+						self.confirm_quit = true;
+						self.set_status("Unsaved changes: s to save, Esc again to quit");
+					} else {
+						self.quit = true;
+					}
+					None
+				}
+				Msg::Redraw => None,
+				Msg::KeyPressed(key) => {
+					// We already updated the keycap in the keyboard component
+					// Force redraw when any key is pressed
+					self.redraw = true;
+					self.presses.record(&key);
+					self.unsaved_presses.record(&key);
+					if self.heatmap {
+						self.show_heatmap();
+					}
+					None
+				}
+				Msg::ToggleHeatmap => {
+					self.heatmap = !self.heatmap;
+					self.show_heatmap();
+					if self.heatmap {
+						self.set_status(format!(
+							"Heatmap of {} presses · F2 hide · F3 reset",
+							self.presses.total()
+						));
+					} else {
+						self.set_status(self.help());
+					}
+					None
+				}
+				Msg::ResetStats if !self.confirm_reset => {
+					self.confirm_reset = true;
+					self.set_status(format!(
+						"F3 again to forget all {} presses",
+						self.presses.total()
+					));
+					None
+				}
+				Msg::ResetStats => {
+					self.confirm_reset = false;
+					match self.reset_stats() {
+						Ok(()) => self.set_status("Press counts reset"),
+						Err(err) => self.set_status(format!("{:#}", err)),
+					}
 					None
 				}
-				Msg::CounterChanged(v) => {
+				Msg::EditKey(code) => {
 					assert!(self
 						.app
-						.attr(
-							&Id::SimpleCounter,
-							Attribute::Text,
-							AttrValue::String(format!("Counter: {}", v))
-						)
+						.mount(Id::KeyPicker, Box::new(KeyPicker::new(code)), Vec::new())
 						.is_ok());
+					assert!(self.app.active(&Id::KeyPicker).is_ok());
 					None
 				}
-				Msg::KeyPressed(_key) => {
-					// We already updated the keycap in the keyboard component
-					// Force redraw when any key is pressed
-					self.redraw = true;
+				Msg::Remap(from, to) => {
+					self.close_popup(Id::KeyPicker);
+					let layer = self.shown_layer();
+					self.edit(|editor| {
+						editor.remap(&layer, from, to)?;
+						Ok(format!(
+							"{}→{}{}",
+							keys::label_of(from),
+							keys::label_of(to),
+							on(&layer)
+						))
+					});
+					None
+				}
+				Msg::Unmap(code) => {
+					let layer = self.shown_layer();
+					self.edit(|editor| {
+						Ok(if editor.unmap(&layer, code)? {
+							format!("{} unmapped{}", keys::label_of(code), on(&layer))
+						} else {
+							format!("{} is not mapped{}", keys::label_of(code), on(&layer))
+						})
+					});
+					None
+				}
+				Msg::EditCancelled => {
+					self.close_popup(Id::KeyPicker);
+					None
+				}
+				Msg::SaveRequested => {
+					if let Some(editor) = &self.editor {
+						let preview = SavePreview::new(editor.path().display().to_string(), editor.diff());
+						assert!(self
+							.app
+							.mount(Id::SavePreview, Box::new(preview), Vec::new())
+							.is_ok());
+						assert!(self.app.active(&Id::SavePreview).is_ok());
+					}
+					None
+				}
+				Msg::SaveConfirmed => {
+					self.close_popup(Id::SavePreview);
+					if let Some(editor) = self.editor.as_mut() {
+						let status = match editor.save() {
+							Ok(()) => format!("Saved {}", editor.path().display()),
+							Err(err) => format!("{:#}", err),
+						};
+						self.set_status(status);
+					}
+					None
+				}
+				Msg::SaveCancelled => {
+					self.close_popup(Id::SavePreview);
+					None
+				}
+				Msg::ShowLayer(layer) => {
+					self.show_layer(layer);
+					None
+				}
+				Msg::NextLayer => {
+					self.show_layer((self.layer + 1) % self.layers.len().max(1));
+					None
+				}
+				Msg::PreviousLayer => {
+					let count = self.layers.len().max(1);
+					self.show_layer((self.layer + count - 1) % count);
 					None
 				}
 			}
@@ -102,3 +606,49 @@ where
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::config::Config;
+
+	#[test]
+	fn labels_remapped_keys() {
+		let config: Config = r#"
+name = "x"
+
+[[mappings]]
+original_key = "caps"
+tap = "Esc"
+hold = "LCtrl"
+
+[[mappings]]
+original_key = "RightAlt"
+target_key = "MO(nav)"
+
+[layers.nav]
+H = "Left"
+"#
+		.parse()
+		.unwrap();
+		let keymap = Keymap::from_config(&config).unwrap();
+		let legends = keymap_legends(&keymap, 0);
+		let text = |key: &str| -> Vec<(String, Color)> {
+			legends[key]
+				.iter()
+				.map(|span| (span.content.clone(), span.fg))
+				.collect()
+		};
+
+		assert_eq!(
+			text("CapsLock"),
+			[
+				("Esc".to_string(), Color::LightYellow),
+				("Ctrl".to_string(), Color::LightYellow)
+			]
+		);
+		assert_eq!(text("RightAlt"), [("nav".to_string(), Color::LightBlue)]);
+		assert_eq!(legends.len(), 2);
+	}
+}
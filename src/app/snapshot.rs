@@ -0,0 +1,215 @@
+//! Golden snapshots of the keyboard view: scripted events go through the
+//! real `Application`, and each frame is compared, text and colours, with
+//! a file under `src/app/snapshots`. Run the tests with
+//! `UPDATE_SNAPSHOTS=1` to write the files after an intended change.
+
+use std::cell::RefCell;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use evdev::KeyCode;
+use tuirealm::application::PollStrategy;
+use tuirealm::event::{Key, KeyEvent, KeyModifiers};
+use tuirealm::ratatui::buffer::Buffer;
+use tuirealm::ratatui::style::Style;
+use tuirealm::terminal::TerminalBridge;
+use tuirealm::{Event, EventListenerCfg};
+
+use super::headless::{Script, TestTerminalAdapter};
+use super::model::Model;
+use crate::config::edit::ConfigEditor;
+use crate::layout::Layout;
+use crate::UserEvent;
+
+/// Letters naming the styles in a snapshot, in order of appearance.
+const STYLE_NAMES: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+struct Harness {
+	model: Model<TestTerminalAdapter>,
+	script: Script,
+	screen: Rc<RefCell<Buffer>>,
+}
+
+impl Harness {
+	fn new(width: u16, height: u16, layout: &str, editor: Option<ConfigEditor>) -> Self {
+		let adapter = TestTerminalAdapter::new(width, height);
+		let screen = adapter.screen();
+		let script = Script::default();
+		// No ticks, so every tick of the application takes one scripted event
+		let listener = EventListenerCfg::default()
+			.poll_timeout(Duration::from_secs(2))
+			.add_port(script.port(), Duration::from_millis(1), 1);
+		let layout = Layout::bundled(layout).expect("bundled layout");
+		let model = Model::with_terminal(
+			TerminalBridge::new(adapter),
+			listener,
+			&layout,
+			editor,
+			None,
+		);
+		Self {
+			model,
+			script,
+			screen,
+		}
+	}
+
+	fn send(&mut self, event: Event<UserEvent>) {
+		self.script.push(event);
+		let messages = self
+			.model
+			.app
+			.tick(PollStrategy::Once)
+			.expect("application tick");
+		self.model.process(messages);
+	}
+
+	fn key(&mut self, key: Key) {
+		self.send(Event::Keyboard(KeyEvent::new(key, KeyModifiers::NONE)));
+	}
+
+	fn key_down(&mut self, code: KeyCode) {
+		self.send(Event::User(UserEvent::KeyDown(code)));
+	}
+
+	/// Draws the view and compares it with the golden file `name`.
+	fn assert_snapshot(&mut self, name: &str) {
+		self.model.view();
+		let actual = render(&self.screen.borrow());
+		let path = golden_path(name);
+		if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+			fs::write(&path, &actual).unwrap();
+			return;
+		}
+		let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+			panic!(
+				"no snapshot {}, run with UPDATE_SNAPSHOTS=1 to write it",
+				path.display()
+			)
+		});
+		if actual != expected {
+			let new = path.with_extension("snap.new");
+			fs::write(&new, &actual).unwrap();
+			panic!(
+				"{} differs from the view, drawn to {}; run with UPDATE_SNAPSHOTS=1 if the change is intended",
+				path.display(),
+				new.display()
+			);
+		}
+	}
+}
+
+fn golden_path(name: &str) -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR"))
+		.join("src/app/snapshots")
+		.join(format!("{}.snap", name))
+}
+
+/// The buffer's text, then the same grid with a letter per cell naming
+/// its style, then what each letter stands for.
+fn render(buffer: &Buffer) -> String {
+	let area = buffer.area;
+	let mut styles: Vec<Style> = Vec::new();
+	let mut text = String::new();
+	let mut grid = String::new();
+	for y in area.top()..area.bottom() {
+		for x in area.left()..area.right() {
+			let cell = &buffer[(x, y)];
+			text.push_str(cell.symbol());
+			let style = cell.style();
+			let idx = styles
+				.iter()
+				.position(|&known| known == style)
+				.unwrap_or_else(|| {
+					styles.push(style);
+					styles.len() - 1
+				});
+			grid.push(
+				STYLE_NAMES
+					.chars()
+					.nth(idx)
+					.expect("too many styles for one snapshot"),
+			);
+		}
+		text.push('\n');
+		grid.push('\n');
+	}
+
+	let mut out = format!("{}x{}\n\n{}\n{}\n", area.width, area.height, text, grid);
+	for (style, name) in styles.iter().zip(STYLE_NAMES.chars()) {
+		let _ = writeln!(
+			out,
+			"{} fg={:?} bg={:?} modifiers={:?}",
+			name,
+			style.fg.unwrap_or_default(),
+			style.bg.unwrap_or_default(),
+			style.add_modifier
+		);
+	}
+	out
+}
+
+#[test]
+fn lights_held_keys() {
+	let mut harness = Harness::new(80, 18, "ansi-60", None);
+	harness.assert_snapshot("ansi_60");
+
+	harness.key_down(KeyCode::KEY_LEFTSHIFT);
+	harness.key_down(KeyCode::KEY_A);
+	harness.assert_snapshot("ansi_60_shift_a");
+}
+
+#[test]
+fn colours_caps_by_presses() {
+	// A line more than the keycaps need, for the colour scale
+	let mut harness = Harness::new(80, 19, "ansi-60", None);
+	for code in [
+		KeyCode::KEY_E,
+		KeyCode::KEY_E,
+		KeyCode::KEY_E,
+		KeyCode::KEY_T,
+	] {
+		harness.key_down(code);
+	}
+	harness.key(Key::Function(2));
+	harness.assert_snapshot("ansi_60_heatmap");
+}
+
+#[test]
+fn edits_layers_of_a_config() {
+	let file = tempfile::NamedTempFile::new().unwrap();
+	fs::write(
+		file.path(),
+		r#"name = "snapshot"
+
+[[mappings]]
+original_key = "CapsLock"
+tap = "Esc"
+hold = "LCtrl"
+
+[[mappings]]
+original_key = "RightAlt"
+target_key = "MO(nav)"
+
+[layers.nav]
+H = "Left"
+J = "Down"
+K = "Up"
+L = "Right"
+"#,
+	)
+	.unwrap();
+	let editor = ConfigEditor::open(file.path()).unwrap();
+
+	let mut harness = Harness::new(100, 18, "ansi-60", Some(editor));
+	harness.key(Key::Down);
+	harness.key(Key::Right);
+	harness.assert_snapshot("edit_base");
+
+	harness.key(Key::Tab);
+	harness.assert_snapshot("edit_nav");
+}
@@ -0,0 +1,199 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use crate::device::{DeviceInfo, DeviceProbe, EvdevProbe};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// Where to write the configuration
+	#[arg(short, long, default_value = "config.toml")]
+	pub output: PathBuf,
+
+	/// Overwrite the output file if it already exists
+	#[arg(long)]
+	pub force: bool,
+
+	/// Pick the target device from the detected keyboards
+	#[arg(short, long)]
+	pub interactive: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let device = if args.interactive {
+		let stdin = io::stdin();
+		let stderr = io::stderr();
+		choose_device(&EvdevProbe, &mut stdin.lock(), &mut stderr.lock())?
+	} else {
+		None
+	};
+
+	let mut options = OpenOptions::new();
+	options.write(true);
+	if args.force {
+		options.create(true).truncate(true);
+	} else {
+		options.create_new(true);
+	}
+
+	let mut file = match options.open(&args.output) {
+		Ok(file) => file,
+		Err(err) if err.kind() == io::ErrorKind::AlreadyExists => bail!(
+			"{} already exists, pass --force to overwrite it",
+			args.output.display()
+		),
+		Err(err) => {
+			return Err(err).with_context(|| format!("Cannot create {}", args.output.display()))
+		}
+	};
+
+	file
+		.write_all(render(device.as_ref()).as_bytes())
+		.with_context(|| format!("Cannot write {}", args.output.display()))?;
+
+	println!("Wrote starter configuration to {}", args.output.display());
+	Ok(())
+}
+
+/// Lists the detected keyboards and reads the user's choice. An empty answer
+/// leaves the device unset so the mappings apply to the first keyboard found.
+pub fn choose_device<R: BufRead, W: Write>(
+	probe: &dyn DeviceProbe,
+	input: &mut R,
+	out: &mut W,
+) -> Result<Option<DeviceInfo>> {
+	let keyboards: Vec<DeviceInfo> = probe
+		.devices()?
+		.into_iter()
+		.filter(DeviceInfo::is_keyboard)
+		.collect();
+
+	if keyboards.is_empty() {
+		writeln!(out, "No keyboards detected, leaving `device` unset")?;
+		return Ok(None);
+	}
+
+	writeln!(out, "Detected keyboards:")?;
+	for (idx, keyboard) in keyboards.iter().enumerate() {
+		writeln!(
+			out,
+			"  {}) {} ({})",
+			idx + 1,
+			keyboard.name,
+			keyboard.path.display()
+		)?;
+	}
+
+	loop {
+		write!(
+			out,
+			"Select a keyboard [1-{}, empty for none]: ",
+			keyboards.len()
+		)?;
+		out.flush()?;
+
+		let mut answer = String::new();
+		if input.read_line(&mut answer)? == 0 {
+			return Ok(None);
+		}
+
+		let answer = answer.trim();
+		if answer.is_empty() {
+			return Ok(None);
+		}
+
+		match answer.parse::<usize>() {
+			Ok(n) if (1..=keyboards.len()).contains(&n) => return Ok(Some(keyboards[n - 1].clone())),
+			_ => writeln!(out, "'{}' is not a valid choice", answer)?,
+		}
+	}
+}
+
+/// Renders the commented starter configuration.
+pub fn render(device: Option<&DeviceInfo>) -> String {
+	let device_line = match device {
+		Some(device) => format!("device = {}", toml::Value::String(device.name.clone())),
+		None => "# device = \"Device Name\"".to_string(),
+	};
+
+	format!(
+		r#"# geekCaps configuration
+
+name = "My Keyboard Layout"
+
+# Device to apply mappings to (optional).
+# Run `geekCaps list-devices` to see the names of connected keyboards.
+{device_line}
+
+# List of key mappings
+[[mappings]]
+original_key = "CapsLock"
+target_key = "Escape"
+
+# [[mappings]]
+# original_key = "Escape"
+# target_key = "CapsLock"
+
+# Hold these keys together to stop remapping if a mapping locks you out.
+# [panic]
+# keys = ["LeftShift", "RightShift", "Escape"]
+# hold = 2000
+"#
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use std::path::PathBuf;
+
+	use super::*;
+	use crate::config::{Config, DeviceSelector};
+	use crate::device::FakeProbe;
+
+	fn keyboard(name: &str, path: &str) -> DeviceInfo {
+		DeviceInfo {
+			name: name.to_string(),
+			path: PathBuf::from(path),
+			phys: None,
+			vendor: 0x046d,
+			product: 0xc52b,
+			has_keys: true,
+			alphanumeric: true,
+		}
+	}
+
+	#[test]
+	fn chooses_among_keyboards_only() {
+		let mut mouse = keyboard("USB Mouse", "/dev/input/event5");
+		mouse.alphanumeric = false;
+		let probe = FakeProbe(vec![
+			keyboard("Laptop", "/dev/input/event3"),
+			mouse,
+			keyboard("Keychron K2", "/dev/input/event7"),
+		]);
+
+		let mut out = Vec::new();
+		let chosen = choose_device(&probe, &mut Cursor::new("9\n2\n"), &mut out).unwrap();
+
+		assert_eq!(chosen.unwrap().name, "Keychron K2");
+		assert!(String::from_utf8(out)
+			.unwrap()
+			.contains("'9' is not a valid choice"));
+	}
+
+	#[test]
+	fn rendered_config_is_valid_toml() {
+		let device = keyboard("Keychron \"K2\"", "/dev/input/event7");
+		let parsed: Config = render(Some(&device)).parse().unwrap();
+		assert_eq!(
+			parsed.device,
+			Some(DeviceSelector::Name("Keychron \"K2\"".to_string()))
+		);
+
+		let parsed: Config = render(None).parse().unwrap();
+		assert_eq!(parsed.device, None);
+	}
+}
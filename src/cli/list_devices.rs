@@ -0,0 +1,147 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+
+use crate::device::{DeviceInfo, DeviceProbe, EvdevProbe};
+
+#[derive(Debug, Default, clap::Args)]
+pub struct Args {
+	/// Print the device list as JSON instead of a table
+	#[arg(long)]
+	pub json: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let stdout = io::stdout();
+	print(&args, &EvdevProbe, &mut stdout.lock())
+}
+
+pub fn print<W: Write>(args: &Args, probe: &dyn DeviceProbe, out: &mut W) -> Result<()> {
+	let devices = probe.devices()?;
+
+	if args.json {
+		serde_json::to_writer_pretty(&mut *out, &devices)?;
+		writeln!(out)?;
+		return Ok(());
+	}
+
+	if devices.is_empty() {
+		writeln!(
+			out,
+			"No input devices found (is /dev/input readable by this user?)"
+		)?;
+		return Ok(());
+	}
+
+	write_table(&devices, out)
+}
+
+fn write_table<W: Write>(devices: &[DeviceInfo], out: &mut W) -> Result<()> {
+	let header = ["PATH", "NAME", "PHYS", "ID", "EV_KEY", "KEYBOARD"];
+	let rows: Vec<[String; 6]> = devices
+		.iter()
+		.map(|device| {
+			[
+				device.path.display().to_string(),
+				device.name.clone(),
+				device.phys.clone().unwrap_or_else(|| "-".to_string()),
+				format!("{:04x}:{:04x}", device.vendor, device.product),
+				yes_no(device.has_keys).to_string(),
+				yes_no(device.is_keyboard()).to_string(),
+			]
+		})
+		.collect();
+
+	let mut widths = header.map(str::len);
+	for row in &rows {
+		for (width, cell) in widths.iter_mut().zip(row) {
+			*width = (*width).max(cell.chars().count());
+		}
+	}
+
+	write_row(out, &header, &widths)?;
+	for row in &rows {
+		write_row(out, row, &widths)?;
+	}
+	Ok(())
+}
+
+fn write_row<W: Write, S: AsRef<str>>(out: &mut W, cells: &[S], widths: &[usize]) -> Result<()> {
+	let line = cells
+		.iter()
+		.zip(widths)
+		.map(|(cell, width)| format!("{:<width$}", cell.as_ref(), width = width))
+		.collect::<Vec<_>>()
+		.join("  ");
+	writeln!(out, "{}", line.trim_end())?;
+	Ok(())
+}
+
+fn yes_no(flag: bool) -> &'static str {
+	if flag {
+		"yes"
+	} else {
+		"no"
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use super::*;
+	use crate::device::FakeProbe;
+
+	fn fake_devices() -> FakeProbe {
+		FakeProbe(vec![
+			DeviceInfo {
+				name: "AT Translated Set 2 keyboard".to_string(),
+				path: PathBuf::from("/dev/input/event3"),
+				phys: Some("isa0060/serio0/input0".to_string()),
+				vendor: 0x0001,
+				product: 0x0001,
+				has_keys: true,
+				alphanumeric: true,
+			},
+			DeviceInfo {
+				name: "Power Button".to_string(),
+				path: PathBuf::from("/dev/input/event0"),
+				phys: None,
+				vendor: 0,
+				product: 0x0001,
+				has_keys: true,
+				alphanumeric: false,
+			},
+		])
+	}
+
+	#[test]
+	fn prints_table() {
+		let mut out = Vec::new();
+		print(&Args::default(), &fake_devices(), &mut out).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		let lines: Vec<&str> = out.lines().collect();
+
+		assert_eq!(lines.len(), 3);
+		assert!(lines[0].starts_with("PATH"));
+		assert!(lines[1].contains("0001:0001"));
+		assert!(lines[1]
+			.split_whitespace()
+			.rev()
+			.take(2)
+			.all(|c| c == "yes"));
+		assert!(lines[2].contains("Power Button"));
+		assert!(lines[2].ends_with("no"));
+	}
+
+	#[test]
+	fn prints_json() {
+		let mut out = Vec::new();
+		print(&Args { json: true }, &fake_devices(), &mut out).unwrap();
+		let parsed: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+		assert_eq!(parsed[0]["name"], "AT Translated Set 2 keyboard");
+		assert_eq!(parsed[0]["alphanumeric"], true);
+		assert_eq!(parsed[1]["phys"], serde_json::Value::Null);
+	}
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+pub mod init;
+pub mod list_devices;
+pub mod monitor;
+pub mod record;
+pub mod replay;
+pub mod start;
+pub mod stats;
+pub mod validate;
+
+#[derive(Debug, Parser)]
+#[command(name = "geekCaps", version, about)]
+pub struct Cli {
+	#[command(subcommand)]
+	pub command: Option<Command>,
+
+	/// Input device the keyboard view follows (default: first keyboard)
+	#[arg(short, long)]
+	pub device: Option<PathBuf>,
+
+	/// Keyboard layout to draw: a bundled name (ansi-60, tkl, full-size,
+	/// iso, ortho-40) or a TOML/JSON layout file
+	#[arg(short, long, default_value = "ansi-60")]
+	pub layout: String,
+
+	/// Config to show on the keyboard and edit: Enter on a key remaps it
+	#[arg(short, long)]
+	pub config: Option<PathBuf>,
+
+	/// File the heatmap's press counts are kept in (default: the config's
+	/// [stats] file, else none and only this session is counted)
+	#[arg(long)]
+	pub stats: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+	/// List available input devices
+	ListDevices(list_devices::Args),
+	/// Write a starter configuration file
+	Init(init::Args),
+	/// Start remapping keys with a configuration
+	Start(start::Args),
+	/// Show the most pressed keys and bigrams counted while remapping
+	Stats(stats::Args),
+	/// Print each key event beside what the remapping makes of it, without
+	/// grabbing the device
+	Monitor(monitor::Args),
+	/// Check a configuration for mistakes without applying it
+	Validate(validate::Args),
+	/// Capture the raw events of an input device to a file
+	Record(record::Args),
+	/// Play a recording through the remapping engine, the virtual keyboard
+	/// or the keyboard view
+	Replay(replay::Args),
+}
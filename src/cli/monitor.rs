@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::remap;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// Configuration whose remapping to show
+	#[arg(short, long)]
+	pub config: PathBuf,
+
+	/// Input device to watch, overriding `device` in the config
+	#[arg(short, long)]
+	pub device: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let config = Config::load(&args.config)?;
+	remap::monitor::run(&config, args.device.as_deref())
+}
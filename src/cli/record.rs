@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use evdev::Device;
+
+use crate::device::{self, EvdevProbe};
+use crate::recording::{self, RawEvent};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// File to write the events to, one JSON object per line
+	#[arg(short, long)]
+	pub output: PathBuf,
+
+	/// Input device to record (default: first keyboard)
+	#[arg(short, long)]
+	pub device: Option<PathBuf>,
+}
+
+/// Writes every event of the device to the file until interrupted. The
+/// device is not grabbed, so typing goes on reaching the desktop.
+pub fn run(args: Args) -> Result<()> {
+	let path = match args.device {
+		Some(path) => path,
+		None => device::select(&EvdevProbe, None)?.path,
+	};
+	let mut device = Device::open(&path).with_context(|| {
+		format!(
+			"Cannot open {} (is this user in the `input` group?)",
+			path.display()
+		)
+	})?;
+	let file = File::create(&args.output)
+		.with_context(|| format!("Cannot create {}", args.output.display()))?;
+	let mut out = BufWriter::new(file);
+
+	eprintln!(
+		"Recording {} ({}) to {}, press Ctrl+C to stop",
+		device.name().unwrap_or("unnamed device"),
+		path.display(),
+		args.output.display()
+	);
+	loop {
+		let events: Vec<RawEvent> = device
+			.fetch_events()
+			.with_context(|| format!("Cannot read from {}", path.display()))?
+			.map(|event| RawEvent::from_input(&event))
+			.collect();
+		// Flush every batch, as Ctrl+C is the usual way out
+		recording::write(&mut out, &events)?;
+		out
+			.flush()
+			.with_context(|| format!("Cannot write {}", args.output.display()))?;
+	}
+}
@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use crate::app::input::ReplayPort;
+use crate::app::model::Model;
+use crate::config::Config;
+use crate::keys;
+use crate::layout::Layout;
+use crate::recording::{self, Playback};
+use crate::remap::{KeyEvent, Keymap, VirtualKeyboard};
+
+/// Gives the desktop time to notice a new virtual keyboard, or the first
+/// events go nowhere.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// Recording made with `record`
+	pub file: PathBuf,
+
+	/// Configuration to remap the recording with (default: play it as is)
+	#[arg(short, long)]
+	pub config: Option<PathBuf>,
+
+	/// Type the result on a virtual keyboard
+	#[arg(long)]
+	pub uinput: bool,
+
+	/// Light the result on the keyboard view
+	#[arg(long)]
+	pub view: bool,
+
+	/// Keyboard layout for --view: a bundled name or a layout file
+	#[arg(short, long, default_value = "ansi-60")]
+	pub layout: String,
+
+	/// How many times faster than recorded to play
+	#[arg(long, default_value_t = 1.0)]
+	pub speed: f64,
+}
+
+/// Without `--uinput` or `--view` the events and their remapping are
+/// printed at once rather than played.
+pub fn run(args: Args) -> Result<()> {
+	if !(args.speed > 0.0 && args.speed.is_finite()) {
+		bail!("--speed must be above 0, not {}", args.speed);
+	}
+	let raw = recording::load(&args.file)?;
+	let origin = raw[0].time();
+	let input: Vec<KeyEvent> = raw.iter().filter_map(|event| event.key_event()).collect();
+	let output = match &args.config {
+		Some(path) => recording::remap(Keymap::from_config(&Config::load(path)?)?, &input),
+		None => input.clone(),
+	};
+
+	if !args.uinput && !args.view {
+		let stdout = io::stdout();
+		return print(&input, &output, origin, &mut stdout.lock());
+	}
+
+	let virtual_keyboard = if args.uinput {
+		let keyboard = VirtualKeyboard::create(None, output.iter().map(|event| event.code))?;
+		thread::sleep(SETTLE_TIME);
+		Some(keyboard)
+	} else {
+		None
+	};
+	let playback = Playback::new(output, origin, args.speed, Instant::now());
+
+	if args.view {
+		let layout = Layout::find(&args.layout)?;
+		let port = ReplayPort::new(playback, virtual_keyboard);
+		Model::new(Some(Box::new(port)), &layout, None, None).run();
+		return Ok(());
+	}
+
+	let Some(mut keyboard) = virtual_keyboard else {
+		return Ok(());
+	};
+	eprintln!(
+		"Replaying {} at {}x, press Ctrl+C to stop",
+		args.file.display(),
+		args.speed
+	);
+	play(playback, &mut keyboard)
+}
+
+/// Types the playback on the virtual keyboard, then lets go of every key.
+fn play(mut playback: Playback, keyboard: &mut VirtualKeyboard) -> Result<()> {
+	let mut last = Duration::ZERO;
+	while let Some(at) = playback.next_at() {
+		thread::sleep(at.saturating_duration_since(Instant::now()));
+		let due = playback.due(Instant::now());
+		if let Some(event) = due.last() {
+			last = event.time;
+			keyboard.emit(due, last)?;
+		}
+	}
+	keyboard.release_all(last)
+}
+
+/// Lists the recorded key events and what became of them, in time order
+/// and in seconds from the start of the recording.
+pub fn print<W: Write>(
+	input: &[KeyEvent],
+	output: &[KeyEvent],
+	origin: Duration,
+	out: &mut W,
+) -> Result<()> {
+	let mut timeline: Vec<(&str, &KeyEvent)> = input
+		.iter()
+		.map(|event| ("in ", event))
+		.chain(output.iter().map(|event| ("out", event)))
+		.collect();
+	timeline.sort_by_key(|(_, event)| event.time);
+
+	for (direction, event) in timeline {
+		writeln!(
+			out,
+			"{:>9.3}  {}  {} {}",
+			event.time.saturating_sub(origin).as_secs_f64(),
+			direction,
+			keys::name_of(event.code),
+			event.state.name()
+		)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use evdev::KeyCode;
+
+	use super::*;
+	use crate::remap::KeyState;
+
+	#[test]
+	fn prints_events_beside_their_remapping() {
+		let at = |ms: u64| Duration::from_secs(100) + Duration::from_millis(ms);
+		let input = [
+			KeyEvent::new(KeyCode::KEY_CAPSLOCK, KeyState::Pressed, at(0)),
+			KeyEvent::new(KeyCode::KEY_CAPSLOCK, KeyState::Released, at(120)),
+		];
+		let output = [
+			KeyEvent::new(KeyCode::KEY_ESC, KeyState::Pressed, at(120)),
+			KeyEvent::new(KeyCode::KEY_ESC, KeyState::Released, at(120)),
+		];
+		let mut out = Vec::new();
+		print(&input, &output, at(0), &mut out).unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			"    0.000  in   CapsLock down\n    0.120  in   CapsLock up\n    0.120  out  Escape down\n    0.120  out  Escape up\n"
+		);
+	}
+}
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::remap;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// Configuration file to apply, reloaded whenever it is saved
+	#[arg(short, long)]
+	pub config: PathBuf,
+
+	/// Input device to grab, overriding `device` in the config
+	#[arg(short, long)]
+	pub device: Option<PathBuf>,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	remap::run(&args.config, args.device.as_deref())
+}
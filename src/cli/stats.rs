@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::stats::{self, Stats, StatsLock};
+
+/// Width of the longest bar in the hour histogram.
+const BAR_WIDTH: u64 = 40;
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// Stats file to read (default: ~/.local/share/geekcaps/stats.json)
+	#[arg(short, long)]
+	pub file: Option<PathBuf>,
+
+	/// How many keys and bigrams to list
+	#[arg(short = 'n', long, default_value_t = 10)]
+	pub top: usize,
+
+	/// Forget every press counted so far
+	#[arg(long)]
+	pub reset: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let path = args
+		.file
+		.clone()
+		.or_else(Stats::default_path)
+		.context("No home directory, pass the stats file with --file")?;
+
+	if args.reset {
+		StatsLock::wait(&path)?.reset()?;
+		println!("Forgot the presses counted in {}", path.display());
+		return Ok(());
+	}
+
+	let stats = Stats::load(&path)?;
+	let stdout = io::stdout();
+	print(&stats, args.top, &mut stdout.lock())
+}
+
+pub fn print<W: Write>(stats: &Stats, top: usize, out: &mut W) -> Result<()> {
+	let total = stats.total();
+	if total == 0 {
+		writeln!(
+			out,
+			"No presses counted yet, add a [stats] table to the config to count them"
+		)?;
+		return Ok(());
+	}
+	writeln!(out, "{} keystrokes", total)?;
+
+	let share = |count: u64| count as f64 * 100.0 / total as f64;
+	writeln!(out, "\nTop keys")?;
+	for (key, count) in stats::top(&stats.keys, top) {
+		writeln!(out, "  {:<14} {:>8} {:>5.1}%", key, count, share(count))?;
+	}
+
+	if !stats.bigrams.is_empty() {
+		writeln!(out, "\nTop bigrams")?;
+		for (pair, count) in stats::top(&stats.bigrams, top) {
+			writeln!(out, "  {:<14} {:>8}", pair, count)?;
+		}
+	}
+
+	if !stats.layers.is_empty() {
+		writeln!(out, "\nLayers")?;
+		for (layer, count) in stats::top(&stats.layers, usize::MAX) {
+			writeln!(out, "  {:<14} {:>8} {:>5.1}%", layer, count, share(count))?;
+		}
+	}
+
+	let busiest = stats.hours.iter().copied().max().unwrap_or(0);
+	if busiest > 0 {
+		writeln!(out, "\nHours (UTC)")?;
+		for (hour, &count) in stats.hours.iter().enumerate() {
+			let bar = "█".repeat((count * BAR_WIDTH).div_ceil(busiest) as usize);
+			writeln!(out, "  {:02}  {:>8} {}", hour, count, bar)?;
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use super::*;
+
+	#[test]
+	fn prints_top_keys() {
+		let stats = Stats {
+			keys: BTreeMap::from([
+				("A".to_string(), 3),
+				("Space".to_string(), 5),
+				("B".to_string(), 2),
+			]),
+			bigrams: BTreeMap::from([("A Space".to_string(), 2)]),
+			..Stats::default()
+		};
+		let mut out = Vec::new();
+		print(&stats, 2, &mut out).unwrap();
+		let text = String::from_utf8(out).unwrap();
+		assert_eq!(
+			text,
+			"10 keystrokes\n\nTop keys\n  Space                 5  50.0%\n  A                     3  30.0%\n\nTop bigrams\n  A Space               2\n"
+		);
+	}
+}
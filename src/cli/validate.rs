@@ -0,0 +1,135 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::lint::{self, Diagnostic, Severity};
+use crate::config::Config;
+
+/// Exit status when the config has errors.
+pub const EXIT_ERRORS: i32 = 1;
+/// Exit status when the config only has warnings.
+pub const EXIT_WARNINGS: i32 = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+	#[default]
+	Text,
+	Json,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+	/// Configuration file to check
+	#[arg(short, long)]
+	pub config: PathBuf,
+
+	/// How to print the problems found
+	#[arg(long, value_enum, default_value_t = Format::Text)]
+	pub format: Format,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+	file: String,
+	errors: usize,
+	warnings: usize,
+	diagnostics: &'a [Diagnostic],
+}
+
+/// Checks the config and prints what is wrong with it. Returns the exit
+/// status: 0 when clean, [`EXIT_ERRORS`] or [`EXIT_WARNINGS`] otherwise.
+pub fn run(args: Args) -> Result<i32> {
+	let diagnostics = match Config::load(&args.config) {
+		Ok(config) => lint::lint(&config),
+		// A config that does not parse is one more problem to report
+		Err(err) => vec![Diagnostic {
+			severity: Severity::Error,
+			code: "syntax",
+			field: String::new(),
+			message: format!("{:#}", err),
+		}],
+	};
+	let stdout = io::stdout();
+	print(&args, &diagnostics, &mut stdout.lock())?;
+
+	let errors = count(&diagnostics, Severity::Error);
+	Ok(if errors > 0 {
+		EXIT_ERRORS
+	} else if diagnostics.is_empty() {
+		0
+	} else {
+		EXIT_WARNINGS
+	})
+}
+
+fn count(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+	diagnostics
+		.iter()
+		.filter(|diagnostic| diagnostic.severity == severity)
+		.count()
+}
+
+pub fn print<W: Write>(args: &Args, diagnostics: &[Diagnostic], out: &mut W) -> Result<()> {
+	let report = Report {
+		file: args.config.display().to_string(),
+		errors: count(diagnostics, Severity::Error),
+		warnings: count(diagnostics, Severity::Warning),
+		diagnostics,
+	};
+
+	if args.format == Format::Json {
+		serde_json::to_writer_pretty(&mut *out, &report)?;
+		writeln!(out)?;
+		return Ok(());
+	}
+
+	if diagnostics.is_empty() {
+		writeln!(out, "{} is valid", report.file)?;
+		return Ok(());
+	}
+	for diagnostic in diagnostics {
+		writeln!(out, "{}", diagnostic)?;
+	}
+	writeln!(
+		out,
+		"\n{}: {} error(s), {} warning(s)",
+		report.file, report.errors, report.warnings
+	)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn prints_diagnostics() {
+		let diagnostics = [Diagnostic {
+			severity: Severity::Warning,
+			code: "unreachable-layer",
+			field: "layers.nav".to_string(),
+			message: "no key switches to `nav`".to_string(),
+		}];
+		let mut args = Args {
+			config: PathBuf::from("config.toml"),
+			format: Format::Text,
+		};
+
+		let mut out = Vec::new();
+		print(&args, &diagnostics, &mut out).unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			"warning[unreachable-layer] layers.nav: no key switches to `nav`\n\nconfig.toml: 0 error(s), 1 warning(s)\n"
+		);
+
+		args.format = Format::Json;
+		let mut out = Vec::new();
+		print(&args, &diagnostics, &mut out).unwrap();
+		let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+		assert_eq!(json["warnings"], 1);
+		assert_eq!(json["diagnostics"][0]["severity"], "warning");
+		assert_eq!(json["diagnostics"][0]["code"], "unreachable-layer");
+	}
+}
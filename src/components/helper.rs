@@ -1,7 +1,7 @@
-use tuirealm::props::{Alignment, Borders, Color, Style};
+use tuirealm::props::{Alignment, Borders};
 use tuirealm::ratatui::widgets::Block;
 
-pub fn get_block<'a>(props: Borders, title: (String, Alignment), focus: bool) -> Block<'a> {
+pub fn get_block<'a>(props: Borders, title: (String, Alignment)) -> Block<'a> {
 	Block::default()
 		.borders(props.sides)
 		.border_style(props.style()) // Always use the border style from props
@@ -9,4 +9,3 @@ pub fn get_block<'a>(props: Borders, title: (String, Alignment), focus: bool) ->
 		.title(title.0)
 		.title_alignment(title.1)
 }
-
@@ -1,78 +1,131 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
+
+use evdev::KeyCode;
 use tuirealm::command::{Cmd, CmdResult};
-use tuirealm::event::{Key, KeyModifiers};
-use tuirealm::props::{Alignment, BorderType, Borders, Color, TextModifiers};
-use tuirealm::ratatui::layout::{Constraint, Direction, Layout, Rect};
-use tuirealm::{
-	AttrValue, Attribute, Component, Event, Frame, MockComponent, NoUserEvent, Props, State,
+use tuirealm::event::{Key, KeyEvent, KeyModifiers};
+use tuirealm::props::{
+	Alignment, BorderType, Borders, Color, PropPayload, PropValue, TextModifiers, TextSpan,
 };
+use tuirealm::ratatui::layout::Rect;
+use tuirealm::ratatui::style::Style;
+use tuirealm::ratatui::text::{Line, Span};
+use tuirealm::{AttrValue, Attribute, Component, Event, Frame, MockComponent, Props, State};
+
+use crate::keys;
+use crate::layout::Layout as PhysicalLayout;
+use crate::{Msg, UserEvent};
+
+use super::keycap::{CapState, Keycap};
+
+/// Legends of the layer to display, as an `AttrValue::Payload` list of
+/// `[registry key name, legend, legend...]` vectors built by
+/// [`legends_value`]. Keys missing from the list show only their physical
+/// label.
+pub const LEGENDS: Attribute = Attribute::Custom("legends");
+
+/// Flag set when the displayed layer falls through to the layers below
+/// wherever it has no legend, dimming those caps.
+pub const TRANSPARENT: Attribute = Attribute::Custom("transparent");
+
+/// Press counts to colour the caps by, as an `AttrValue::Payload` map from
+/// registry key name to a `u64` built by [`heatmap_value`]. Any other
+/// value turns the heatmap off.
+pub const HEATMAP: Attribute = Attribute::Custom("heatmap");
+
+/// Heatmap colours from the least to the most pressed key, dark enough
+/// for white labels.
+const HEAT: [(u8, u8, u8); 5] = [
+	(25, 45, 90),
+	(20, 105, 110),
+	(55, 135, 40),
+	(165, 125, 0),
+	(180, 40, 30),
+];
+
+/// Background for a key pressed `count` times when the busiest key was
+/// pressed `max` times. The scale is logarithmic, as a few keys take most
+/// presses.
+pub fn heat_color(count: u64, max: u64) -> Color {
+	if max == 0 {
+		return heat_at(0.0);
+	}
+	heat_at(((count as f32).ln_1p() / (max as f32).ln_1p()).clamp(0.0, 1.0))
+}
 
-use crate::Msg;
-
-use super::keycap::Keycap;
+/// The colour `t` of the way along [`HEAT`].
+fn heat_at(t: f32) -> Color {
+	let scaled = t * (HEAT.len() - 1) as f32;
+	let low = (scaled.floor() as usize).min(HEAT.len() - 2);
+	let t = scaled - low as f32;
+	let (from, to) = (HEAT[low], HEAT[low + 1]);
+	let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+	Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
 
 pub struct Keyboard {
 	props: Props,
 	keycaps: Vec<Vec<Keycap>>,
+	/// Set once evdev events arrive; terminal key events are then only
+	/// used to quit, as they cannot tell which physical keys are down.
+	live: bool,
+	/// Row and index within the row of the cap being edited, `None` when
+	/// the view is read-only. Terminal keys then move it instead of
+	/// lighting caps.
+	cursor: Option<(usize, usize)>,
 }
 
 impl Default for Keyboard {
 	fn default() -> Self {
-		Self {
-			props: Props::default(),
-			keycaps: vec![vec![Keycap::new("?")]],
-		}
+		Self::with_custom_layout(&PhysicalLayout::from_labels(vec![vec!["?"]]))
 	}
 }
 
 impl Keyboard {
+	/// The bundled ANSI 60% keyboard.
 	pub fn new() -> Self {
-		let qwerty_layout = vec![
-			vec![
-				"Esc", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "-", "=", "\\", "`",
-			],
-			vec![
-				"Tab:7", "Q", "W", "E", "R", "T", "Y", "U", "I", "O", "P", "[", "]", "Bs:8",
-			],
-			vec![
-				"Caps:9", "A", "S", "D", "F", "G", "H", "J", "K", "L", ";", "'", "Enter:11",
-			],
-			vec![
-				"Shift:11", "Z", "X", "C", "V", "B", "N", "M", ",", ".", "/", "Shift:9", "Fn",
-			],
-			vec![":8", "Alt", "Cmd:9", "Space:27", "Cmd:9", "Alt"],
-		];
-
-		let mut keycaps = Vec::new();
-		for row in qwerty_layout {
-			let mut keycap_row = Vec::new();
-			for key in row {
-				keycap_row.push(Keycap::new(key));
-			}
-			keycaps.push(keycap_row);
-		}
-
-		Self {
-			props: Props::default(),
-			keycaps,
-		}
+		Self::with_custom_layout(&PhysicalLayout::default())
 	}
 
-	pub fn with_custom_layout(layout: Vec<Vec<&str>>) -> Self {
-		let mut keycaps = Vec::new();
-		for row in layout {
-			let mut keycap_row = Vec::new();
-			for key in row {
-				keycap_row.push(Keycap::new(key));
-			}
-			keycaps.push(keycap_row);
+	/// Lays out one keycap per cap of `layout`, converting key units to
+	/// terminal cells.
+	pub fn with_custom_layout(layout: &PhysicalLayout) -> Self {
+		let mut keycaps: Vec<Vec<Keycap>> = layout.rows.iter().map(|_| Vec::new()).collect();
+		for placed in layout.placed() {
+			let shape = placed
+				.areas()
+				.iter()
+				.map(|area| {
+					let (x, y, width, height) = area.cells();
+					Rect::new(x, y, width, height)
+				})
+				.collect();
+			keycaps[placed.row].push(
+				Keycap::new(&placed.cap.label)
+					.shape(shape)
+					.with_code(placed.cap.code)
+					.at(placed.row, placed.col),
+			);
 		}
 
 		Self {
 			props: Props::default(),
 			keycaps,
+			live: false,
+			cursor: None,
 		}
 	}
 
+	/// Shows a cursor that picks caps to remap.
+	pub fn editable(mut self) -> Self {
+		self.cursor = self
+			.keycaps
+			.iter()
+			.position(|row| !row.is_empty())
+			.map(|row| (row, 0));
+		self
+	}
+
 	pub fn borders(mut self, b: Borders) -> Self {
 		self.attr(Attribute::Borders, AttrValue::Borders(b));
 		self
@@ -88,50 +141,182 @@ impl Keyboard {
 		self
 	}
 
-	fn highlight_keycap(&mut self, label: &str) -> bool {
-		for row in self.keycaps.iter_mut() {
-			for keycap in row.iter_mut() {
-				let mut borders = keycap
-					.query(Attribute::Borders)
-					.unwrap_or(AttrValue::Borders(
-						Borders::default().modifiers(BorderType::Rounded),
-					))
-					.unwrap_borders();
-
-				let label = keycap.get_label();
-				if label.starts_with(':') && label[1..].chars().all(|c| c.is_digit(10)) {
-					let transparent_borders = Borders::default().color(Color::Black); // Use black color for invisible borders
-					keycap.attr(Attribute::Borders, AttrValue::Borders(transparent_borders));
-				} else {
-					borders = borders.color(Color::DarkGray);
-					keycap.attr(Attribute::Borders, AttrValue::Borders(borders));
-				}
-			}
+	pub fn legends(mut self, legends: HashMap<String, Vec<TextSpan>>) -> Self {
+		self.attr(LEGENDS, legends_value(legends));
+		self
+	}
+
+	fn apply_legends(&mut self, legends: &HashMap<String, Vec<TextSpan>>) {
+		for keycap in self.keycaps.iter_mut().flatten() {
+			let legend = keycap
+				.code()
+				.and_then(|code| legends.get(&keys::name_of(code)))
+				.cloned()
+				.unwrap_or_default();
+			keycap.set_legends(legend);
 		}
+	}
+
+	/// Physical keys to light for a terminal key event. Terminals only
+	/// report the character and modifiers, so the left modifiers stand in
+	/// for either side.
+	fn term_keys(key_event: &KeyEvent) -> HashSet<KeyCode> {
+		let mut codes: HashSet<KeyCode> = keys::from_term(key_event.code)
+			.map(|def| def.code)
+			.into_iter()
+			.collect();
+		if key_event.modifiers.contains(KeyModifiers::ALT) {
+			codes.insert(KeyCode::KEY_LEFTALT);
+		}
+		if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+			codes.insert(KeyCode::KEY_LEFTCTRL);
+		}
+		if key_event.modifiers.contains(KeyModifiers::SHIFT) {
+			codes.insert(KeyCode::KEY_LEFTSHIFT);
+		}
+		codes
+	}
 
-		if label.is_empty() {
-			return false;
+	/// Lights every cap for `code`.
+	fn press(&mut self, code: KeyCode) {
+		for keycap in self.caps_mut(code) {
+			keycap.press();
 		}
+	}
 
-		for row in self.keycaps.iter_mut() {
-			for keycap in row.iter_mut() {
-				if keycap.get_display_label().to_lowercase() == label.to_lowercase() {
-					let mut borders = keycap
-						.query(Attribute::Borders)
-						.unwrap_or(AttrValue::Borders(
-							Borders::default().modifiers(BorderType::Rounded),
-						))
-						.unwrap_borders();
+	/// Starts fading every cap for `code`.
+	fn release(&mut self, code: KeyCode, now: Instant) {
+		for keycap in self.caps_mut(code) {
+			keycap.release(now);
+		}
+	}
 
-					borders = borders.color(Color::Yellow);
-					keycap.attr(Attribute::Borders, AttrValue::Borders(borders));
+	fn caps_mut(&mut self, code: KeyCode) -> impl Iterator<Item = &mut Keycap> {
+		self
+			.keycaps
+			.iter_mut()
+			.flatten()
+			.filter(move |keycap| keycap.code() == Some(code))
+	}
 
-					return true;
-				}
+	/// The key under the cursor, if the cap stands for one.
+	pub fn cursor_code(&self) -> Option<KeyCode> {
+		let (row, col) = self.cursor?;
+		self.keycaps[row][col].code()
+	}
+
+	/// Moves the cursor along its row, or to the nearest cap in the next
+	/// row up or down.
+	fn move_cursor(&mut self, key: Key) {
+		let Some((row, col)) = self.cursor else {
+			return;
+		};
+		let center = |keycap: &Keycap| {
+			let bounds = keycap.bounds();
+			bounds.x * 2 + bounds.width
+		};
+		let from = center(&self.keycaps[row][col]);
+		let rows = match key {
+			Key::Left => {
+				self.cursor = Some((row, col.saturating_sub(1)));
+				return;
+			}
+			Key::Right => {
+				self.cursor = Some((row, (col + 1).min(self.keycaps[row].len() - 1)));
+				return;
+			}
+			Key::Up => (0..row).rev().collect::<Vec<_>>(),
+			Key::Down => (row + 1..self.keycaps.len()).collect(),
+			_ => return,
+		};
+		let next = rows.into_iter().find_map(|row| {
+			let col = self.keycaps[row]
+				.iter()
+				.enumerate()
+				.min_by_key(|(_, keycap)| center(keycap).abs_diff(from))?
+				.0;
+			Some((row, col))
+		});
+		if next.is_some() {
+			self.cursor = next;
+		}
+	}
+
+	/// Terminal keys while editing: arrows move the cursor, Enter remaps the
+	/// key under it, Delete drops its mapping and `s` saves. Tab and the
+	/// digits pick the layer shown.
+	fn edit(&mut self, key_event: KeyEvent) -> Option<Msg> {
+		match key_event.code {
+			Key::Tab => Some(Msg::NextLayer),
+			Key::BackTab => Some(Msg::PreviousLayer),
+			Key::Char(digit @ '1'..='9') => Some(Msg::ShowLayer(digit as usize - '1' as usize)),
+			Key::Left | Key::Right | Key::Up | Key::Down => {
+				self.move_cursor(key_event.code);
+				Some(Msg::Redraw)
 			}
+			Key::Enter => self.cursor_code().map(Msg::EditKey),
+			Key::Delete | Key::Backspace => self.cursor_code().map(Msg::Unmap),
+			Key::Char('s') => Some(Msg::SaveRequested),
+			_ => None,
+		}
+	}
+
+	/// Whether terminal keys are meant for the keyboard rather than a popup
+	/// in front of it. Subscriptions forward them either way.
+	fn focused(&self) -> bool {
+		self
+			.props
+			.get_or(Attribute::Focus, AttrValue::Flag(true))
+			.unwrap_flag()
+	}
+
+	/// Press counts by registry key name while the heatmap is on.
+	fn heat(&self) -> Option<HashMap<String, u64>> {
+		let Some(AttrValue::Payload(PropPayload::Map(counts))) = self.props.get(HEATMAP) else {
+			return None;
+		};
+		Some(
+			counts
+				.into_iter()
+				.filter_map(|(key, count)| match count {
+					PropValue::U64(count) => Some((key, count)),
+					_ => None,
+				})
+				.collect(),
+		)
+	}
+
+	/// Draws the heatmap's scale on the line under the caps.
+	fn view_heat_legend(&self, frame: &mut Frame, area: Rect, max: u64) {
+		let bottom = self
+			.keycaps
+			.iter()
+			.flatten()
+			.map(|keycap| keycap.bounds().bottom())
+			.max()
+			.unwrap_or(0);
+		let line = Rect::new(area.x, area.y.saturating_add(bottom), area.width, 1).intersection(area);
+		if line.is_empty() {
+			return;
 		}
+		let dim = Style::default().fg(Color::Gray);
+		let mut spans = vec![Span::styled("Presses  0 ", dim)];
+		spans.extend(
+			(0..=10).map(|step| Span::styled("█", Style::default().fg(heat_at(step as f32 / 10.0)))),
+		);
+		spans.push(Span::styled(format!(" {}", max), dim));
+		frame.render_widget(Line::from(spans), line);
+	}
 
-		false
+	/// Keys whose caps are currently held down.
+	pub fn held(&self) -> HashSet<KeyCode> {
+		self
+			.keycaps
+			.iter()
+			.flatten()
+			.filter(|keycap| keycap.state() == CapState::Down)
+			.filter_map(Keycap::code)
+			.collect()
 	}
 }
 
@@ -170,88 +355,73 @@ impl MockComponent for Keyboard {
 				),
 			)
 			.unwrap_borders();
-		let _title = self
-			.props
-			.get_or(
-				Attribute::Title,
-				AttrValue::Title((String::default(), Alignment::Center)),
-			)
-			.unwrap_title();
-		let _focus = self
+
+		let transparent = self
 			.props
-			.get_or(Attribute::Focus, AttrValue::Flag(false))
+			.get_or(TRANSPARENT, AttrValue::Flag(false))
 			.unwrap_flag();
 
-		let row_count = self.keycaps.len();
-		// Use original size
-		let row_constraints = vec![Constraint::Length(3); row_count];
-		let row_chunks = Layout::default()
-			.direction(Direction::Vertical)
-			.constraints(row_constraints)
-			.split(area);
+		let heat = self.heat();
+		let max_heat = heat
+			.as_ref()
+			.and_then(|heat| heat.values().max().copied())
+			.unwrap_or(0);
 
+		let now = Instant::now();
+		let cursor = self.cursor;
 		for (row_idx, row) in self.keycaps.iter_mut().enumerate() {
-			if let Some(row_chunk) = row_chunks.get(row_idx) {
-				let keycap_count = row.len();
-				// Create constraints based on key labels with widths
-				let mut keycap_constraints = Vec::with_capacity(keycap_count);
-				for keycap in row.iter() {
-					let label = keycap.get_label();
-
-					// Parse width from labels like "key:width"
-					if let Some(pos) = label.find(':') {
-						if let Ok(width) = label[pos + 1..].parse::<u16>() {
-							keycap_constraints.push(Constraint::Length(width));
-						} else {
-							keycap_constraints.push(Constraint::Length(5)); // Default if parsing fails
-						}
-					} else {
-						keycap_constraints.push(Constraint::Length(5)); // Default width
-					}
+			for (col_idx, keycap) in row.iter_mut().enumerate() {
+				// Caps sit on an absolute grid; those past the edge are clipped
+				let bounds = keycap.bounds();
+				let cap_area = Rect::new(
+					area.x.saturating_add(bounds.x),
+					area.y.saturating_add(bounds.y),
+					bounds.width,
+					bounds.height,
+				)
+				.intersection(area);
+				if cap_area.is_empty() {
+					continue;
 				}
 
-				let keycap_chunks = Layout::default()
-					.direction(Direction::Horizontal)
-					.constraints(keycap_constraints)
-					.split(*row_chunk);
-
-				for (col_idx, keycap) in row.iter_mut().enumerate() {
-					if let Some(keycap_chunk) = keycap_chunks.get(col_idx) {
-						// Important: Set Text attribute from the label for display
-						// Use display label (without width suffix) for rendering
-						let display_label = keycap.get_display_label();
-						keycap.attr(Attribute::Text, AttrValue::String(display_label));
-
-						// Set text-related properties
-						keycap.attr(Attribute::TextAlign, AttrValue::Alignment(alignment));
-						keycap.attr(Attribute::TextProps, AttrValue::TextModifiers(modifiers));
-
-						// Set colors - keeping foreground white for better visibility
-						keycap.attr(Attribute::Foreground, AttrValue::Color(foreground));
-						keycap.attr(Attribute::Background, AttrValue::Color(background));
-
-						// Ensure keycaps have rounded borders (if not already set)
-						if keycap.query(Attribute::Borders).is_none() {
-							// Check if this is a spacer keycap (starting with ":") or the space key
-							let label = keycap.get_label();
-							// Check for pure spacer keycap or space key
-							if label.starts_with(':') && label[1..].chars().all(|c| c.is_digit(10)) {
-								// For spacer keycaps and space key, use transparent borders (no visible borders)
-								let transparent_borders = Borders::default().color(Color::Black); // Use black color for invisible borders
-								keycap.attr(Attribute::Borders, AttrValue::Borders(transparent_borders));
-							} else {
-								keycap.attr(
-									Attribute::Borders,
-									AttrValue::Borders(default_borders.clone()),
-								);
-							}
-						}
-
-						keycap.view(frame, *keycap_chunk);
-					}
+				// The cap draws its legends itself, or its label when it has none
+				keycap.attr(Attribute::TextAlign, AttrValue::Alignment(alignment));
+				keycap.attr(Attribute::TextProps, AttrValue::TextModifiers(modifiers));
+
+				// Set colors - keeping foreground white for better visibility
+				let dimmed = transparent && keycap.legends().is_empty();
+				let foreground = if dimmed { Color::DarkGray } else { foreground };
+				keycap.attr(Attribute::Foreground, AttrValue::Color(foreground));
+				let count = heat
+					.as_ref()
+					.zip(keycap.code())
+					.and_then(|(heat, code)| heat.get(&keys::name_of(code)).copied())
+					.unwrap_or(0);
+				let background = if count > 0 {
+					heat_color(count, max_heat)
+				} else {
+					background
+				};
+				keycap.attr(Attribute::Background, AttrValue::Color(background));
+
+				let mut borders = default_borders.clone();
+				if cursor == Some((row_idx, col_idx)) {
+					borders = borders
+						.modifiers(BorderType::Double)
+						.color(Color::LightCyan);
 				}
+				if let Some(color) = keycap.highlight(now) {
+					borders = borders.color(color);
+				}
+				keycap.attr(Attribute::Borders, AttrValue::Borders(borders));
+
+				keycap.view(frame, cap_area);
 			}
 		}
+
+		if heat.is_some() {
+			self.view_heat_legend(frame, area, max_heat);
+		}
 	}
 
 	fn query(&self, attr: Attribute) -> Option<AttrValue> {
@@ -259,6 +429,12 @@ impl MockComponent for Keyboard {
 	}
 
 	fn attr(&mut self, attr: Attribute, value: AttrValue) {
+		if attr == LEGENDS {
+			match &value {
+				AttrValue::Payload(payload) => self.apply_legends(&unpack_legends(payload)),
+				_ => self.apply_legends(&HashMap::new()),
+			}
+		}
 		self.props.set(attr, value);
 	}
 
@@ -271,57 +447,245 @@ impl MockComponent for Keyboard {
 	}
 }
 
-impl Component<Msg, NoUserEvent> for Keyboard {
-	fn on(&mut self, ev: Event<NoUserEvent>) -> Option<Msg> {
-		match ev {
-			Event::Keyboard(key_event) => {
-				if key_event.code == Key::Esc && key_event.modifiers == KeyModifiers::NONE {
-					return Some(Msg::AppClose);
-				}
-
-				let key_str = match key_event.code {
-					Key::Char(' ') => "space".to_string(),
-					Key::Char(ch) => ch.to_string(),
-					Key::Backspace => "Bs".to_string(),
-					Key::Enter => "Enter".to_string(),
-					Key::Left => "←".to_string(),
-					Key::Right => "→".to_string(),
-					Key::Up => "↑".to_string(),
-					Key::Down => "↓".to_string(),
-					Key::Tab => "tab".to_string(),
-					_ => String::new(), // Empty for unknown keys
-				};
+/// Packs press counts for the [`HEATMAP`] attribute.
+pub fn heatmap_value(counts: &BTreeMap<String, u64>) -> AttrValue {
+	AttrValue::Payload(PropPayload::Map(
+		counts
+			.iter()
+			.map(|(key, count)| (key.clone(), PropValue::U64(*count)))
+			.collect(),
+	))
+}
 
-				let mut modified = false;
+/// Packs legends for the [`LEGENDS`] attribute.
+pub fn legends_value(legends: HashMap<String, Vec<TextSpan>>) -> AttrValue {
+	AttrValue::Payload(PropPayload::Linked(
+		legends
+			.into_iter()
+			.map(|(key, spans)| {
+				PropPayload::Vec(
+					std::iter::once(PropValue::Str(key))
+						.chain(spans.into_iter().map(PropValue::TextSpan))
+						.collect(),
+				)
+			})
+			.collect(),
+	))
+}
 
-				// First clear all highlights
-				self.highlight_keycap(""); // Will reset all keycaps but not find any to highlight
+/// Unpacks a [`LEGENDS`] payload, skipping malformed entries.
+fn unpack_legends(payload: &PropPayload) -> HashMap<String, Vec<TextSpan>> {
+	let PropPayload::Linked(entries) = payload else {
+		return HashMap::new();
+	};
+	entries
+		.iter()
+		.filter_map(|entry| {
+			let PropPayload::Vec(values) = entry else {
+				return None;
+			};
+			let (PropValue::Str(key), spans) = values.split_first()? else {
+				return None;
+			};
+			let spans = spans
+				.iter()
+				.filter_map(|value| match value {
+					PropValue::TextSpan(span) => Some(span.clone()),
+					_ => None,
+				})
+				.collect();
+			Some((key.clone(), spans))
+		})
+		.collect()
+}
 
-				// Then apply new highlights
-				if key_event.modifiers.contains(KeyModifiers::ALT) {
-					self.highlight_keycap("Alt");
-					modified = true;
+impl Component<Msg, UserEvent> for Keyboard {
+	fn on(&mut self, ev: Event<UserEvent>) -> Option<Msg> {
+		match ev {
+			Event::User(UserEvent::KeyDown(code)) => {
+				self.live = true;
+				self.press(code);
+				Some(Msg::KeyPressed(keys::name_of(code)))
+			}
+			Event::User(UserEvent::KeyUp(code)) => {
+				self.live = true;
+				self.release(code, Instant::now());
+				Some(Msg::Redraw)
+			}
+			Event::Tick => {
+				let now = Instant::now();
+				let mut fading = false;
+				for keycap in self.keycaps.iter_mut().flatten() {
+					fading |= keycap.settle(now);
 				}
-				if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-					self.highlight_keycap("Caps");
-					modified = true;
+				fading.then_some(Msg::Redraw)
+			}
+			Event::Keyboard(_) if !self.focused() => None,
+			Event::Keyboard(key_event) => {
+				match key_event.code {
+					Key::Esc if key_event.modifiers == KeyModifiers::NONE => return Some(Msg::AppClose),
+					Key::Function(2) => return Some(Msg::ToggleHeatmap),
+					Key::Function(3) => return Some(Msg::ResetStats),
+					_ => {}
 				}
-				if key_event.modifiers.contains(KeyModifiers::SHIFT) {
-					self.highlight_keycap("Shift");
-					modified = true;
+				if self.cursor.is_some() {
+					return self.edit(key_event);
 				}
-
-				if !key_str.is_empty() {
-					self.highlight_keycap(&key_str);
-					return Some(Msg::KeyPressed(key_str));
-				} else if modified {
-					return Some(Msg::KeyPressed("modifier".to_string()));
+				if self.live {
+					return None;
 				}
 
-				// Always return a message when keys are pressed to ensure redraw happens
-				Some(Msg::KeyPressed("".to_string()))
+				// Terminals report no key-up, so the previous keys fade as
+				// soon as another one is typed
+				let now = Instant::now();
+				let pressed = Self::term_keys(&key_event);
+				for code in self.held() {
+					if !pressed.contains(&code) {
+						self.release(code, now);
+					}
+				}
+				for code in pressed {
+					self.press(code);
+				}
+				// Only physical presses are reported: these are guesses
+				Some(Msg::Redraw)
 			}
 			_ => None,
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tracks_held_physical_keys() {
+		let mut keyboard = Keyboard::new();
+		let codes: Vec<KeyCode> = keyboard
+			.keycaps
+			.iter()
+			.flatten()
+			.filter_map(Keycap::code)
+			.collect();
+		assert!(codes.contains(&KeyCode::KEY_LEFTSHIFT));
+		assert!(codes.contains(&KeyCode::KEY_RIGHTSHIFT));
+		assert!(codes.contains(&KeyCode::KEY_LEFTCTRL));
+
+		keyboard.on(Event::User(UserEvent::KeyDown(KeyCode::KEY_LEFTSHIFT)));
+		keyboard.on(Event::User(UserEvent::KeyDown(KeyCode::KEY_RIGHTSHIFT)));
+		keyboard.on(Event::User(UserEvent::KeyDown(KeyCode::KEY_CAPSLOCK)));
+		keyboard.on(Event::User(UserEvent::KeyUp(KeyCode::KEY_LEFTSHIFT)));
+		assert_eq!(
+			keyboard.held(),
+			HashSet::from([KeyCode::KEY_RIGHTSHIFT, KeyCode::KEY_CAPSLOCK])
+		);
+
+		// Each Shift cap has its own position and state.
+		let shifts: Vec<(usize, usize, CapState)> = keyboard
+			.keycaps
+			.iter()
+			.flatten()
+			.filter(|keycap| keycap.get_display_label() == "Shift")
+			.map(|keycap| {
+				let (row, col) = keycap.position();
+				(row, col, keycap.state())
+			})
+			.collect();
+		assert!(matches!(
+			shifts.as_slice(),
+			[(3, 0, CapState::Released(_)), (3, 11, CapState::Down)]
+		));
+
+		// Terminal events no longer move the highlight once evdev is live.
+		let ctrl_c = KeyEvent::new(Key::Char('c'), KeyModifiers::CONTROL);
+		assert_eq!(keyboard.on(Event::Keyboard(ctrl_c)), None);
+	}
+
+	#[test]
+	fn moves_the_edit_cursor() {
+		let mut keyboard =
+			Keyboard::with_custom_layout(&PhysicalLayout::bundled("iso").unwrap()).editable();
+		let mut press = |key: Key| keyboard.on(Event::Keyboard(KeyEvent::new(key, KeyModifiers::NONE)));
+		assert_eq!(press(Key::Enter), Some(Msg::EditKey(KeyCode::KEY_GRAVE)));
+
+		// Down lands on the cap nearest below, Right stops at the row's end
+		press(Key::Down);
+		press(Key::Down);
+		press(Key::Right);
+		assert_eq!(press(Key::Enter), Some(Msg::EditKey(KeyCode::KEY_A)));
+		for _ in 0..20 {
+			press(Key::Right);
+		}
+		assert_eq!(press(Key::Delete), Some(Msg::Unmap(KeyCode::KEY_BACKSLASH)));
+		press(Key::Up);
+		assert_eq!(
+			press(Key::Enter),
+			Some(Msg::EditKey(KeyCode::KEY_RIGHTBRACE))
+		);
+		press(Key::Right);
+		assert_eq!(press(Key::Enter), Some(Msg::EditKey(KeyCode::KEY_ENTER)));
+		assert_eq!(press(Key::Char('s')), Some(Msg::SaveRequested));
+		assert_eq!(press(Key::Tab), Some(Msg::NextLayer));
+		assert_eq!(press(Key::Char('2')), Some(Msg::ShowLayer(1)));
+
+		// Keys meant for a popup in front of the keyboard are ignored
+		keyboard.attr(Attribute::Focus, AttrValue::Flag(false));
+		let enter = KeyEvent::new(Key::Enter, KeyModifiers::NONE);
+		assert_eq!(keyboard.on(Event::Keyboard(enter)), None);
+	}
+
+	#[test]
+	fn dims_transparent_keys() {
+		use tuirealm::ratatui::backend::TestBackend;
+		use tuirealm::ratatui::Terminal;
+
+		let mut keyboard =
+			Keyboard::with_custom_layout(&PhysicalLayout::from_labels(vec![vec!["H", "J"]])).legends(
+				HashMap::from([("H".to_string(), vec![TextSpan::new("Left")])]),
+			);
+		keyboard.attr(TRANSPARENT, AttrValue::Flag(true));
+
+		let mut terminal = Terminal::new(TestBackend::new(10, 3)).unwrap();
+		terminal
+			.draw(|frame| keyboard.view(frame, frame.area()))
+			.unwrap();
+		let buffer = terminal.backend().buffer();
+		assert_eq!(buffer[(1, 1)].symbol(), "L");
+		assert_eq!(buffer[(1, 1)].fg, Color::White);
+		assert_eq!(buffer[(7, 1)].symbol(), "J");
+		assert_eq!(buffer[(7, 1)].fg, Color::DarkGray);
+	}
+
+	#[test]
+	fn colours_caps_by_presses() {
+		use tuirealm::ratatui::backend::TestBackend;
+		use tuirealm::ratatui::Terminal;
+
+		assert_eq!(heat_color(1, 1), Color::Rgb(180, 40, 30));
+		assert_eq!(heat_color(0, 1), Color::Rgb(25, 45, 90));
+		// Logarithmic: 9 of 99 presses is half way
+		assert_eq!(heat_color(9, 99), Color::Rgb(55, 135, 40));
+
+		let mut keyboard =
+			Keyboard::with_custom_layout(&PhysicalLayout::from_labels(vec![vec!["H", "J", "K"]]));
+		keyboard.attr(
+			HEATMAP,
+			heatmap_value(&BTreeMap::from([
+				("H".to_string(), 99),
+				("J".to_string(), 9),
+			])),
+		);
+
+		let mut terminal = Terminal::new(TestBackend::new(30, 4)).unwrap();
+		terminal
+			.draw(|frame| keyboard.view(frame, frame.area()))
+			.unwrap();
+		let buffer = terminal.backend().buffer();
+		assert_eq!(buffer[(2, 1)].bg, Color::Rgb(180, 40, 30));
+		assert_eq!(buffer[(7, 1)].bg, Color::Rgb(55, 135, 40));
+		assert_eq!(buffer[(12, 1)].bg, Color::Reset);
+		let legend: String = (0..30).map(|x| buffer[(x, 3)].symbol()).collect();
+		assert_eq!(legend.trim_end(), "Presses  0 ███████████ 99");
+	}
+}
@@ -1,26 +1,53 @@
+use std::time::{Duration, Instant};
+
+use evdev::KeyCode;
 use tuirealm::command::{Cmd, CmdResult};
-use tuirealm::props::{Alignment, BorderType, Borders, Color, Style, TextModifiers};
+use tuirealm::props::{Alignment, BorderType, Borders, Color, Style, TextModifiers, TextSpan};
 use tuirealm::ratatui::layout::Rect;
+use tuirealm::ratatui::text::{Line, Span};
 use tuirealm::ratatui::widgets::Paragraph;
-use tuirealm::{
-	AttrValue, Attribute, Component, Event, Frame, MockComponent, NoUserEvent, Props, State,
-};
+use tuirealm::{AttrValue, Attribute, Component, Event, Frame, MockComponent, Props, State};
 
-use crate::Msg;
+use crate::{Msg, UserEvent};
 
 use super::helper;
 
+/// How long a released key takes to fade back to its normal border.
+pub const FADE: Duration = Duration::from_millis(400);
+
+/// Border colour of a held key, fading to `IDLE` grey on release.
+const HELD: (u8, u8, u8) = (255, 215, 0);
+const IDLE: (u8, u8, u8) = (128, 128, 128);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CapState {
+	#[default]
+	Up,
+	Down,
+	/// Released at this instant and still fading out.
+	Released(Instant),
+}
+
 pub struct Keycap {
 	props: Props,
 	label: String,
+	/// What the key does under the loaded config, e.g. the tap then the
+	/// hold target of a dual-role key. Drawn inside the cap, moving the
+	/// label to the top border.
+	legends: Vec<TextSpan>,
+	/// The physical key this cap stands for, `None` for blank caps.
+	code: Option<KeyCode>,
+	/// Row and column in the keyboard, unique even among equal labels.
+	position: (usize, usize),
+	/// Terminal cells covered, from the keyboard's top-left corner: the
+	/// body, then the second part of an L-shaped key.
+	shape: Vec<Rect>,
+	state: CapState,
 }
 
 impl Default for Keycap {
 	fn default() -> Self {
-		Self {
-			props: Props::default(),
-			label: "?".to_string(),
-		}
+		Self::new("?")
 	}
 }
 
@@ -40,7 +67,31 @@ impl Keycap {
 		Self {
 			props: Props::default(),
 			label: label.to_string(),
+			legends: Vec::new(),
+			code: None,
+			position: (0, 0),
+			shape: vec![Rect::new(0, 0, 5, 3)],
+			state: CapState::Up,
+		}
+	}
+
+	pub fn at(mut self, row: usize, col: usize) -> Self {
+		self.position = (row, col);
+		self
+	}
+
+	/// Places the cap on the keyboard's cell grid. A second rectangle
+	/// makes an L-shaped key.
+	pub fn shape(mut self, shape: Vec<Rect>) -> Self {
+		if !shape.is_empty() {
+			self.shape = shape;
 		}
+		self
+	}
+
+	pub fn with_code(mut self, code: Option<KeyCode>) -> Self {
+		self.code = code;
+		self
 	}
 
 	pub fn borders(mut self, b: Borders) -> Self {
@@ -72,22 +123,176 @@ impl Keycap {
 		self.label.clone()
 	}
 
+	pub fn code(&self) -> Option<KeyCode> {
+		self.code
+	}
+
+	pub fn position(&self) -> (usize, usize) {
+		self.position
+	}
+
+	/// The smallest rectangle holding the whole cap.
+	pub fn bounds(&self) -> Rect {
+		self
+			.shape
+			.iter()
+			.fold(self.shape[0], |bounds, part| bounds.union(*part))
+	}
+
+	pub fn state(&self) -> CapState {
+		self.state
+	}
+
+	pub fn press(&mut self) {
+		self.state = CapState::Down;
+	}
+
+	pub fn release(&mut self, now: Instant) {
+		if self.state == CapState::Down {
+			self.state = CapState::Released(now);
+		}
+	}
+
+	/// Ends a finished fade. Returns whether the cap is still fading.
+	pub fn settle(&mut self, now: Instant) -> bool {
+		match self.state {
+			CapState::Released(at) if now.duration_since(at) >= FADE => {
+				self.state = CapState::Up;
+				false
+			}
+			CapState::Released(_) => true,
+			_ => false,
+		}
+	}
+
+	/// Border colour for the cap's state at `now`, `None` when it is up.
+	pub fn highlight(&self, now: Instant) -> Option<Color> {
+		match self.state {
+			CapState::Up => None,
+			CapState::Down => Some(Color::Rgb(HELD.0, HELD.1, HELD.2)),
+			CapState::Released(at) => {
+				let elapsed = now.duration_since(at);
+				if elapsed >= FADE {
+					return None;
+				}
+				let t = elapsed.as_secs_f32() / FADE.as_secs_f32();
+				let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+				Some(Color::Rgb(
+					mix(HELD.0, IDLE.0),
+					mix(HELD.1, IDLE.1),
+					mix(HELD.2, IDLE.2),
+				))
+			}
+		}
+	}
+
+	pub fn set_legends(&mut self, legends: Vec<TextSpan>) {
+		self.legends = legends;
+	}
+
+	pub fn legends(&self) -> &[TextSpan] {
+		&self.legends
+	}
+
+	/// The text drawn inside the cap: the legends separated by slashes, or
+	/// the label when there are none.
+	fn text(&self) -> Line<'static> {
+		if self.legends.is_empty() {
+			return Line::from(self.get_display_label());
+		}
+		let separator = Span::styled("/", Style::default().fg(Color::DarkGray));
+		let mut spans = Vec::new();
+		for (idx, legend) in self.legends.iter().enumerate() {
+			if idx > 0 {
+				spans.push(separator.clone());
+			}
+			// Unset colours keep the cap's own
+			let mut style = Style::default().add_modifier(legend.modifiers);
+			if legend.fg != Color::Reset {
+				style = style.fg(legend.fg);
+			}
+			if legend.bg != Color::Reset {
+				style = style.bg(legend.bg);
+			}
+			spans.push(Span::styled(legend.content.clone(), style));
+		}
+		Line::from(spans)
+	}
+
 	pub fn get_display_label(&self) -> String {
-		if let Some(pos) = self.label.find(':') {
-			if pos == 0 {
-				return String::new();
+		self.label.clone()
+	}
+}
+
+impl Keycap {
+	/// Where the body of the cap lands when drawn with its top-left corner
+	/// at `area`'s.
+	fn body(&self, area: Rect) -> Rect {
+		let body = self.shape[0];
+		let bounds = self.bounds();
+		Rect::new(
+			area.x + (body.x - bounds.x),
+			area.y + (body.y - bounds.y),
+			body.width,
+			body.height,
+		)
+	}
+
+	/// Draws the border around the union of an L-shaped cap's rectangles,
+	/// with the cap's top-left corner at `area`'s and clipped to it.
+	fn view_outline(&self, frame: &mut Frame, area: Rect, border: Style, fill: Style) {
+		let bounds = self.bounds();
+		let inside = |x: i32, y: i32| {
+			self.shape.iter().any(|part| {
+				let (left, top) = (part.x as i32, part.y as i32);
+				x >= left && x < left + part.width as i32 && y >= top && y < top + part.height as i32
+			})
+		};
+		// On the outline when any of the eight surrounding cells is outside
+		let edge = |x: i32, y: i32| {
+			inside(x, y) && (-1..=1).any(|dy| (-1..=1).any(|dx| !inside(x + dx, y + dy)))
+		};
+
+		let buffer = frame.buffer_mut();
+		for y in bounds.top()..bounds.bottom() {
+			for x in bounds.left()..bounds.right() {
+				let (cx, cy) = (x as i32, y as i32);
+				if !inside(cx, cy) {
+					continue;
+				}
+				let target = (area.x + x - bounds.x, area.y + y - bounds.y);
+				if !area.contains(target.into()) {
+					continue;
+				}
+				let Some(cell) = buffer.cell_mut(target) else {
+					continue;
+				};
+				if !edge(cx, cy) {
+					cell.set_symbol(" ").set_style(fill);
+					continue;
+				}
+				let symbol = match (
+					edge(cx, cy - 1),
+					edge(cx, cy + 1),
+					edge(cx - 1, cy),
+					edge(cx + 1, cy),
+				) {
+					(false, true, false, true) => "╭",
+					(false, true, true, false) => "╮",
+					(true, false, false, true) => "╰",
+					(true, false, true, false) => "╯",
+					(_, _, false, false) => "│",
+					_ => "─",
+				};
+				cell.set_symbol(symbol).set_style(fill.patch(border));
 			}
-			self.label[0..pos].to_string()
-		} else {
-			self.label.clone()
 		}
 	}
 }
 
 impl MockComponent for Keycap {
 	fn view(&mut self, frame: &mut Frame, area: Rect) {
-		// Use the display label (without width suffix) for rendering
-		let text = self.get_display_label();
+		let text = self.text();
 
 		let alignment = self
 			.props
@@ -125,27 +330,49 @@ impl MockComponent for Keycap {
 				AttrValue::Title((String::default(), Alignment::Center)),
 			)
 			.unwrap_title();
-		let focus = self
-			.props
-			.get_or(Attribute::Focus, AttrValue::Flag(false))
-			.unwrap_flag();
-
-		// Create a block without title for the border only
-		let block = helper::get_block(borders, title, focus);
-
-		// Render the widget with the simple text from label
-		frame.render_widget(
-			Paragraph::new(text)
-				.block(block)
-				.style(
-					Style::default()
-						.fg(foreground)
-						.bg(background)
-						.add_modifier(modifiers),
-				)
-				.alignment(alignment),
-			area,
-		);
+
+		let style = Style::default()
+			.fg(foreground)
+			.bg(background)
+			.add_modifier(modifiers);
+
+		if self.shape.len() > 1 {
+			self.view_outline(frame, area, borders.style(), style);
+			let body = self.body(area);
+			let inner = Rect::new(
+				body.x + 1,
+				body.y + 1,
+				body.width.saturating_sub(2),
+				body.height.saturating_sub(2),
+			)
+			.intersection(area);
+			frame.render_widget(
+				Paragraph::new(text).style(style).alignment(alignment),
+				inner,
+			);
+		} else {
+			// Create a block without title for the border only
+			let block = helper::get_block(borders, title);
+			frame.render_widget(
+				Paragraph::new(text)
+					.block(block)
+					.style(style)
+					.alignment(alignment),
+				area,
+			);
+		}
+
+		// With legends inside, the physical label goes on the top border
+		if !self.legends.is_empty() {
+			let body = self.body(area);
+			let top = Rect::new(body.x + 1, body.y, body.width.saturating_sub(2), 1).intersection(area);
+			frame.render_widget(
+				Line::from(self.get_display_label())
+					.style(Style::default().fg(Color::Gray))
+					.alignment(Alignment::Center),
+				top,
+			);
+		}
 	}
 
 	fn query(&self, attr: Attribute) -> Option<AttrValue> {
@@ -165,8 +392,87 @@ impl MockComponent for Keycap {
 	}
 }
 
-impl Component<Msg, NoUserEvent> for Keycap {
-	fn on(&mut self, _: Event<NoUserEvent>) -> Option<Msg> {
+impl Component<Msg, UserEvent> for Keycap {
+	fn on(&mut self, _: Event<UserEvent>) -> Option<Msg> {
 		None
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fades_after_release() {
+		let start = Instant::now();
+		let mut keycap = Keycap::new("A");
+		assert_eq!(keycap.highlight(start), None);
+
+		keycap.press();
+		assert_eq!(keycap.highlight(start), Some(Color::Rgb(255, 215, 0)));
+
+		keycap.release(start);
+		assert_eq!(keycap.highlight(start), Some(Color::Rgb(255, 215, 0)));
+		assert_eq!(
+			keycap.highlight(start + FADE / 2),
+			Some(Color::Rgb(192, 172, 64))
+		);
+		assert!(keycap.settle(start + FADE / 2));
+		assert!(!keycap.settle(start + FADE));
+		assert_eq!(keycap.state(), CapState::Up);
+	}
+
+	#[test]
+	fn draws_l_shaped_outline() {
+		use tuirealm::ratatui::backend::TestBackend;
+		use tuirealm::ratatui::Terminal;
+
+		// ISO Enter: a tall body under a wider top
+		let mut keycap = Keycap::new("Ent").shape(vec![Rect::new(1, 0, 6, 6), Rect::new(0, 0, 7, 3)]);
+		assert_eq!(keycap.bounds(), Rect::new(0, 0, 7, 6));
+
+		let mut terminal = Terminal::new(TestBackend::new(8, 6)).unwrap();
+		terminal
+			.draw(|frame| keycap.view(frame, frame.area()))
+			.unwrap();
+		let buffer = terminal.backend().buffer();
+		let lines: Vec<String> = (0..6)
+			.map(|y| (0..8).map(|x| buffer[(x, y)].symbol()).collect())
+			.collect();
+		assert_eq!(
+			lines,
+			[
+				"╭─────╮ ",
+				"│  Ent│ ",
+				"╰╮    │ ",
+				" │    │ ",
+				" │    │ ",
+				" ╰────╯ ",
+			]
+		);
+	}
+
+	#[test]
+	fn draws_legends_under_the_label() {
+		use tuirealm::ratatui::backend::TestBackend;
+		use tuirealm::ratatui::Terminal;
+
+		let mut keycap = Keycap::new("Caps").shape(vec![Rect::new(0, 0, 9, 3)]);
+		keycap.set_legends(vec![
+			TextSpan::new("Esc").fg(Color::LightYellow),
+			TextSpan::new("Ctl").fg(Color::LightYellow),
+		]);
+
+		let mut terminal = Terminal::new(TestBackend::new(9, 3)).unwrap();
+		terminal
+			.draw(|frame| keycap.view(frame, frame.area()))
+			.unwrap();
+		let buffer = terminal.backend().buffer();
+		let lines: Vec<String> = (0..3)
+			.map(|y| (0..9).map(|x| buffer[(x, y)].symbol()).collect())
+			.collect();
+		assert_eq!(lines, ["╭─Caps──╮", "│Esc/Ctl│", "╰───────╯"]);
+		assert_eq!(buffer[(1, 1)].fg, Color::LightYellow);
+		assert_eq!(buffer[(4, 1)].fg, Color::DarkGray);
+	}
+}
@@ -0,0 +1,179 @@
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::props::{Alignment, BorderType, Color, PropPayload, PropValue, Style, TextModifiers};
+use tuirealm::ratatui::layout::Rect;
+use tuirealm::ratatui::text::{Line, Span};
+use tuirealm::ratatui::widgets::{Block, Borders, Paragraph};
+use tuirealm::{AttrValue, Attribute, Component, Event, Frame, MockComponent, Props, State};
+
+use crate::{Msg, UserEvent};
+
+/// Side panel listing the config's layers by the digit that shows them,
+/// with the one on the keyboard selected and the one the running daemon
+/// has active marked. Without a config it only names the daemon's layer.
+/// The shown layer is set through [`Attribute::Value`] as a `usize`
+/// payload.
+pub struct LayerPanel {
+	props: Props,
+	layers: Vec<String>,
+	/// Active in the daemon, `None` when it is not running.
+	live: Option<String>,
+}
+
+impl LayerPanel {
+	pub fn new(layers: Vec<String>) -> Self {
+		Self {
+			props: Props::default(),
+			layers,
+			live: None,
+		}
+	}
+
+	pub fn shown(&self) -> usize {
+		match self.props.get(Attribute::Value) {
+			Some(AttrValue::Payload(PropPayload::One(PropValue::Usize(layer)))) => layer,
+			_ => 0,
+		}
+	}
+}
+
+/// The layer the daemon has active, as a string, queried to tell whether
+/// the panel has anything to show.
+pub const LIVE: Attribute = Attribute::Custom("live");
+
+/// Packs a layer for [`LayerPanel`]'s [`Attribute::Value`].
+pub fn layer_value(layer: usize) -> AttrValue {
+	AttrValue::Payload(PropPayload::One(PropValue::Usize(layer)))
+}
+
+impl MockComponent for LayerPanel {
+	fn view(&mut self, frame: &mut Frame, area: Rect) {
+		let hint = if self.live.is_some() {
+			" ● live "
+		} else {
+			" Tab next "
+		};
+		let block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.border_style(Style::default().fg(Color::DarkGray))
+			.title(" Layers ")
+			.title_alignment(Alignment::Center)
+			.title_bottom(Line::from(hint).alignment(Alignment::Center));
+
+		// Without a config only the daemon's layer is known
+		let (layers, shown) = if self.layers.is_empty() {
+			(self.live.iter().cloned().collect(), None)
+		} else {
+			(self.layers.clone(), Some(self.shown()))
+		};
+		let lines: Vec<Line> = layers
+			.iter()
+			.enumerate()
+			.map(|(idx, name)| {
+				let live = self.live.as_deref() == Some(name.as_str());
+				let marker = if live {
+					Span::styled("● ", Style::default().fg(Color::LightGreen))
+				} else {
+					Span::raw("  ")
+				};
+				let mut style = Style::default().fg(if live { Color::LightGreen } else { Color::Gray });
+				if Some(idx) == shown {
+					style = style.bg(Color::DarkGray).add_modifier(TextModifiers::BOLD);
+				}
+				// Digits only reach the first nine layers of a config
+				let digit = if shown.is_some() && idx < 9 {
+					format!("{} ", idx + 1)
+				} else {
+					"  ".to_string()
+				};
+				Line::from(vec![
+					marker,
+					Span::styled(format!("{}{}", digit, name), style),
+				])
+			})
+			.collect();
+
+		frame.render_widget(Paragraph::new(lines).block(block), area);
+	}
+
+	fn query(&self, attr: Attribute) -> Option<AttrValue> {
+		if attr == LIVE {
+			return self.live.clone().map(AttrValue::String);
+		}
+		self.props.get(attr)
+	}
+
+	fn attr(&mut self, attr: Attribute, value: AttrValue) {
+		self.props.set(attr, value);
+	}
+
+	fn state(&self) -> State {
+		State::None
+	}
+
+	fn perform(&mut self, _: Cmd) -> CmdResult {
+		CmdResult::None
+	}
+}
+
+impl Component<Msg, UserEvent> for LayerPanel {
+	fn on(&mut self, ev: Event<UserEvent>) -> Option<Msg> {
+		match ev {
+			Event::User(UserEvent::DaemonLayer(layer)) => {
+				self.live = layer;
+				Some(Msg::Redraw)
+			}
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tuirealm::ratatui::backend::TestBackend;
+	use tuirealm::ratatui::Terminal;
+
+	use super::*;
+
+	#[test]
+	fn marks_shown_and_live_layers() {
+		let mut panel = LayerPanel::new(vec!["base".to_string(), "nav".to_string()]);
+		panel.attr(Attribute::Value, layer_value(1));
+		panel.on(Event::User(UserEvent::DaemonLayer(Some(
+			"base".to_string(),
+		))));
+
+		let mut terminal = Terminal::new(TestBackend::new(14, 4)).unwrap();
+		terminal
+			.draw(|frame| panel.view(frame, frame.area()))
+			.unwrap();
+		let buffer = terminal.backend().buffer();
+		let line = |y: u16| -> String { (0..14).map(|x| buffer[(x, y)].symbol()).collect() };
+		assert_eq!(line(1), "│● 1 base    │");
+		assert_eq!(line(2), "│  2 nav     │");
+		assert_eq!(buffer[(3, 1)].fg, Color::LightGreen);
+		assert_eq!(buffer[(3, 2)].bg, Color::DarkGray);
+
+		panel.on(Event::User(UserEvent::DaemonLayer(None)));
+		assert_eq!(panel.live, None);
+	}
+
+	#[test]
+	fn names_the_live_layer_without_a_config() {
+		let mut panel = LayerPanel::new(Vec::new());
+		assert_eq!(panel.query(LIVE), None);
+		panel.on(Event::User(UserEvent::DaemonLayer(Some("nav".to_string()))));
+		assert_eq!(
+			panel.query(LIVE),
+			Some(AttrValue::String("nav".to_string()))
+		);
+
+		let mut terminal = Terminal::new(TestBackend::new(14, 3)).unwrap();
+		terminal
+			.draw(|frame| panel.view(frame, frame.area()))
+			.unwrap();
+		let buffer = terminal.backend().buffer();
+		let line: String = (0..14).map(|x| buffer[(x, 1)].symbol()).collect();
+		assert_eq!(line, "│●   nav     │");
+	}
+}
@@ -1,9 +1,17 @@
-mod counter;
 mod helper;
 mod keyboard;
 mod keycap;
+mod layers;
+mod picker;
+mod preview;
+mod status;
 
-use super::Msg;
-pub use counter::SimpleCounter;
-pub use keycap::Keycap;
-pub use keyboard::Keyboard;
+use super::{Msg, UserEvent};
+pub use keyboard::{
+	heat_color, heatmap_value, legends_value, Keyboard, HEATMAP, LEGENDS, TRANSPARENT,
+};
+pub use keycap::{CapState, Keycap, FADE};
+pub use layers::{layer_value, LayerPanel, LIVE};
+pub use picker::KeyPicker;
+pub use preview::SavePreview;
+pub use status::StatusBar;
@@ -0,0 +1,246 @@
+use evdev::KeyCode;
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::event::{Key, KeyEvent, KeyModifiers};
+use tuirealm::props::{Alignment, BorderType, Color, Style, TextModifiers};
+use tuirealm::ratatui::layout::{Constraint, Direction, Layout, Rect};
+use tuirealm::ratatui::text::Line;
+use tuirealm::ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use tuirealm::{AttrValue, Attribute, Component, Event, Frame, MockComponent, Props, State};
+
+use crate::keys::{self, KeyDef, KEYS};
+use crate::{Msg, UserEvent};
+
+/// Popup choosing the key a keycap should send: the next key pressed, or
+/// one picked from a searchable list of every key.
+pub struct KeyPicker {
+	props: Props,
+	/// The physical key being remapped.
+	source: KeyCode,
+	searching: bool,
+	query: String,
+	selected: usize,
+}
+
+impl KeyPicker {
+	pub fn new(source: KeyCode) -> Self {
+		Self {
+			props: Props::default(),
+			source,
+			searching: false,
+			query: String::new(),
+			selected: 0,
+		}
+	}
+
+	/// Registry keys whose name, alias or label contains the query: exact
+	/// matches first, then those starting with it.
+	fn matches(&self) -> Vec<&'static KeyDef> {
+		let query = self.query.to_lowercase();
+		let rank = |def: &KeyDef| {
+			let names: Vec<String> = std::iter::once(def.name)
+				.chain(def.aliases.iter().copied())
+				.chain(std::iter::once(def.label))
+				.map(str::to_lowercase)
+				.collect();
+			if names.contains(&query) {
+				Some(0)
+			} else if names.iter().any(|name| name.starts_with(&query)) {
+				Some(1)
+			} else if names.iter().any(|name| name.contains(&query)) {
+				Some(2)
+			} else {
+				None
+			}
+		};
+		let mut found: Vec<(u8, &'static KeyDef)> = KEYS
+			.iter()
+			.filter_map(|def| rank(def).map(|rank| (rank, def)))
+			.collect();
+		found.sort_by_key(|(rank, _)| *rank);
+		found.into_iter().map(|(_, def)| def).collect()
+	}
+
+	fn pick(&self, target: KeyCode) -> Option<Msg> {
+		Some(Msg::Remap(self.source, target))
+	}
+
+	fn search(&mut self, key_event: KeyEvent) -> Option<Msg> {
+		match key_event.code {
+			Key::Esc => return Some(Msg::EditCancelled),
+			Key::Enter => {
+				let target = self.matches().get(self.selected).map(|def| def.code)?;
+				return self.pick(target);
+			}
+			Key::Up => self.selected = self.selected.saturating_sub(1),
+			Key::Down => {
+				let count = self.matches().len();
+				self.selected = (self.selected + 1).min(count.saturating_sub(1));
+			}
+			Key::Backspace => {
+				self.query.pop();
+				self.selected = 0;
+			}
+			Key::Char(ch) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+				self.query.push(ch);
+				self.selected = 0;
+			}
+			_ => return None,
+		}
+		Some(Msg::Redraw)
+	}
+}
+
+/// Keys a terminal does not report on their own, such as modifiers. They
+/// can only be picked by pressing them when the physical keyboard is read.
+fn unseen_by_terminal(code: KeyCode) -> bool {
+	match keys::from_code(code).and_then(|def| def.term_key()) {
+		// Only reported with the kitty keyboard protocol, which is not enabled
+		Some(
+			Key::ShiftLeft
+			| Key::ShiftRight
+			| Key::CtrlLeft
+			| Key::CtrlRight
+			| Key::AltLeft
+			| Key::AltRight
+			| Key::CapsLock
+			| Key::NumLock
+			| Key::ScrollLock
+			| Key::Menu,
+		) => true,
+		Some(_) => false,
+		None => true,
+	}
+}
+
+impl MockComponent for KeyPicker {
+	fn view(&mut self, frame: &mut Frame, area: Rect) {
+		let block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.border_style(Style::default().fg(Color::LightCyan))
+			.title(format!(" Remap {} ", keys::label_of(self.source)))
+			.title_alignment(Alignment::Center);
+		let inner = block.inner(area);
+		frame.render_widget(Clear, area);
+		frame.render_widget(block, area);
+
+		if !self.searching {
+			let text = vec![
+				Line::from(""),
+				Line::from(format!(
+					"Press the key {} should send",
+					keys::label_of(self.source)
+				)),
+				Line::from(""),
+				Line::from("Tab  search the key list").style(Style::default().fg(Color::DarkGray)),
+				Line::from("Esc  cancel").style(Style::default().fg(Color::DarkGray)),
+			];
+			frame.render_widget(Paragraph::new(text).alignment(Alignment::Center), inner);
+			return;
+		}
+
+		let chunks = Layout::default()
+			.direction(Direction::Vertical)
+			.constraints([Constraint::Length(2), Constraint::Min(0)])
+			.split(inner);
+		frame.render_widget(
+			Paragraph::new(format!("Search: {}_", self.query)),
+			chunks[0],
+		);
+
+		let items: Vec<ListItem> = self
+			.matches()
+			.into_iter()
+			.map(|def| ListItem::new(format!("{:<16} {}", def.name, def.label)))
+			.collect();
+		let list = List::new(items).highlight_style(
+			Style::default()
+				.fg(Color::Black)
+				.bg(Color::LightCyan)
+				.add_modifier(TextModifiers::BOLD),
+		);
+		let mut state = ListState::default().with_selected(Some(self.selected));
+		frame.render_stateful_widget(list, chunks[1], &mut state);
+	}
+
+	fn query(&self, attr: Attribute) -> Option<AttrValue> {
+		self.props.get(attr)
+	}
+
+	fn attr(&mut self, attr: Attribute, value: AttrValue) {
+		self.props.set(attr, value);
+	}
+
+	fn state(&self) -> State {
+		State::None
+	}
+
+	fn perform(&mut self, _: Cmd) -> CmdResult {
+		CmdResult::None
+	}
+}
+
+impl Component<Msg, UserEvent> for KeyPicker {
+	fn on(&mut self, ev: Event<UserEvent>) -> Option<Msg> {
+		match ev {
+			Event::Keyboard(key_event) if self.searching => self.search(key_event),
+			Event::Keyboard(key_event) => match key_event.code {
+				Key::Esc => Some(Msg::EditCancelled),
+				Key::Tab if key_event.modifiers == KeyModifiers::NONE => {
+					self.searching = true;
+					Some(Msg::Redraw)
+				}
+				key => keys::from_term(key).and_then(|def| self.pick(def.code)),
+			},
+			Event::User(UserEvent::KeyDown(code)) if !self.searching && unseen_by_terminal(code) => {
+				self.pick(code)
+			}
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn press(picker: &mut KeyPicker, key: Key) -> Option<Msg> {
+		picker.on(Event::Keyboard(KeyEvent::new(key, KeyModifiers::NONE)))
+	}
+
+	#[test]
+	fn picks_the_pressed_key() {
+		let mut picker = KeyPicker::new(KeyCode::KEY_CAPSLOCK);
+		assert_eq!(
+			press(&mut picker, Key::Char('x')),
+			Some(Msg::Remap(KeyCode::KEY_CAPSLOCK, KeyCode::KEY_X))
+		);
+		// Terminals cannot report Ctrl alone, evdev can
+		assert_eq!(
+			picker.on(Event::User(UserEvent::KeyDown(KeyCode::KEY_LEFTCTRL))),
+			Some(Msg::Remap(KeyCode::KEY_CAPSLOCK, KeyCode::KEY_LEFTCTRL))
+		);
+		assert_eq!(
+			picker.on(Event::User(UserEvent::KeyDown(KeyCode::KEY_X))),
+			None
+		);
+		assert_eq!(press(&mut picker, Key::Esc), Some(Msg::EditCancelled));
+	}
+
+	#[test]
+	fn searches_the_key_list() {
+		let mut picker = KeyPicker::new(KeyCode::KEY_CAPSLOCK);
+		press(&mut picker, Key::Tab);
+		for ch in "esc".chars() {
+			press(&mut picker, Key::Char(ch));
+		}
+		assert_eq!(picker.matches()[0].code, KeyCode::KEY_ESC);
+		assert_eq!(
+			press(&mut picker, Key::Enter),
+			Some(Msg::Remap(KeyCode::KEY_CAPSLOCK, KeyCode::KEY_ESC))
+		);
+
+		// Typed letters search rather than pick once the list is open
+		assert_eq!(press(&mut picker, Key::Char('x')), Some(Msg::Redraw));
+	}
+}
@@ -0,0 +1,149 @@
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::event::Key;
+use tuirealm::props::{Alignment, BorderType, Color, Style};
+use tuirealm::ratatui::layout::Rect;
+use tuirealm::ratatui::text::{Line, Span};
+use tuirealm::ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use tuirealm::{AttrValue, Attribute, Component, Event, Frame, MockComponent, Props, State};
+
+use crate::config::edit::DiffLine;
+use crate::{Msg, UserEvent};
+
+/// Lines of unchanged context kept above the first change when opening.
+const CONTEXT: usize = 3;
+
+/// Popup showing how saving would change the config file, asking to
+/// confirm.
+pub struct SavePreview {
+	props: Props,
+	path: String,
+	lines: Vec<DiffLine>,
+	scroll: usize,
+}
+
+impl SavePreview {
+	pub fn new(path: String, lines: Vec<DiffLine>) -> Self {
+		let first_change = lines
+			.iter()
+			.position(|line| !matches!(line, DiffLine::Same(_)))
+			.unwrap_or(0);
+		Self {
+			props: Props::default(),
+			path,
+			lines,
+			scroll: first_change.saturating_sub(CONTEXT),
+		}
+	}
+
+	fn has_changes(&self) -> bool {
+		self
+			.lines
+			.iter()
+			.any(|line| !matches!(line, DiffLine::Same(_)))
+	}
+
+	fn scroll_by(&mut self, lines: isize) -> Option<Msg> {
+		let last = self.lines.len().saturating_sub(1);
+		self.scroll = self.scroll.saturating_add_signed(lines).min(last);
+		Some(Msg::Redraw)
+	}
+}
+
+impl MockComponent for SavePreview {
+	fn view(&mut self, frame: &mut Frame, area: Rect) {
+		let hint = if self.has_changes() {
+			" Enter save · Esc cancel "
+		} else {
+			" Nothing to save · Esc close "
+		};
+		let block = Block::default()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.border_style(Style::default().fg(Color::LightCyan))
+			.title(format!(" Save {} ", self.path))
+			.title_alignment(Alignment::Center)
+			.title_bottom(Line::from(hint).alignment(Alignment::Center));
+
+		let text: Vec<Line> = self
+			.lines
+			.iter()
+			.map(|line| match line {
+				DiffLine::Same(text) => Line::from(Span::styled(
+					format!("  {}", text),
+					Style::default().fg(Color::DarkGray),
+				)),
+				DiffLine::Added(text) => Line::from(Span::styled(
+					format!("+ {}", text),
+					Style::default().fg(Color::LightGreen),
+				)),
+				DiffLine::Removed(text) => Line::from(Span::styled(
+					format!("- {}", text),
+					Style::default().fg(Color::LightRed),
+				)),
+			})
+			.collect();
+
+		frame.render_widget(Clear, area);
+		frame.render_widget(
+			Paragraph::new(text)
+				.block(block)
+				.scroll((self.scroll.min(u16::MAX as usize) as u16, 0)),
+			area,
+		);
+	}
+
+	fn query(&self, attr: Attribute) -> Option<AttrValue> {
+		self.props.get(attr)
+	}
+
+	fn attr(&mut self, attr: Attribute, value: AttrValue) {
+		self.props.set(attr, value);
+	}
+
+	fn state(&self) -> State {
+		State::None
+	}
+
+	fn perform(&mut self, _: Cmd) -> CmdResult {
+		CmdResult::None
+	}
+}
+
+impl Component<Msg, UserEvent> for SavePreview {
+	fn on(&mut self, ev: Event<UserEvent>) -> Option<Msg> {
+		let Event::Keyboard(key_event) = ev else {
+			return None;
+		};
+		match key_event.code {
+			Key::Enter | Key::Char('y') if self.has_changes() => Some(Msg::SaveConfirmed),
+			Key::Esc | Key::Char('n') | Key::Char('q') => Some(Msg::SaveCancelled),
+			Key::Up => self.scroll_by(-1),
+			Key::Down => self.scroll_by(1),
+			Key::PageUp => self.scroll_by(-10),
+			Key::PageDown => self.scroll_by(10),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tuirealm::event::{KeyEvent, KeyModifiers};
+
+	use super::*;
+	use crate::config::edit::diff_lines;
+
+	#[test]
+	fn opens_at_the_first_change() {
+		let old = "a\nb\nc\nd\ne\nf\n";
+		let mut preview = SavePreview::new("x.toml".to_string(), diff_lines(old, "a\nb\nc\nd\ne\nF\n"));
+		assert_eq!(preview.scroll, 2);
+
+		let enter = Event::Keyboard(KeyEvent::new(Key::Enter, KeyModifiers::NONE));
+		assert_eq!(preview.on(enter.clone()), Some(Msg::SaveConfirmed));
+
+		// Without changes there is nothing to confirm
+		let mut unchanged = SavePreview::new("x.toml".to_string(), diff_lines(old, old));
+		assert_eq!(unchanged.on(enter), None);
+	}
+}
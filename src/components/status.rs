@@ -0,0 +1,56 @@
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::props::{Color, Style};
+use tuirealm::ratatui::layout::Rect;
+use tuirealm::ratatui::widgets::Paragraph;
+use tuirealm::{AttrValue, Attribute, Component, Event, Frame, MockComponent, Props, State};
+
+use super::{Msg, UserEvent};
+
+/// One line of help or feedback under the keyboard, set through
+/// [`Attribute::Text`].
+#[derive(Default)]
+pub struct StatusBar {
+	props: Props,
+}
+
+impl StatusBar {
+	pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+		self.attr(Attribute::Text, AttrValue::String(text.into()));
+		self
+	}
+}
+
+impl MockComponent for StatusBar {
+	fn view(&mut self, frame: &mut Frame, area: Rect) {
+		let text = self
+			.props
+			.get_or(Attribute::Text, AttrValue::String(String::new()))
+			.unwrap_string();
+		frame.render_widget(
+			Paragraph::new(text).style(Style::default().fg(Color::Gray)),
+			area,
+		);
+	}
+
+	fn query(&self, attr: Attribute) -> Option<AttrValue> {
+		self.props.get(attr)
+	}
+
+	fn attr(&mut self, attr: Attribute, value: AttrValue) {
+		self.props.set(attr, value);
+	}
+
+	fn state(&self) -> State {
+		State::None
+	}
+
+	fn perform(&mut self, _: Cmd) -> CmdResult {
+		CmdResult::None
+	}
+}
+
+impl Component<Msg, UserEvent> for StatusBar {
+	fn on(&mut self, _: Event<UserEvent>) -> Option<Msg> {
+		None
+	}
+}
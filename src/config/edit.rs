@@ -0,0 +1,338 @@
+//! Changing a config file from the keyboard view. Edits go through the
+//! TOML document rather than [`Config`], so comments and formatting
+//! survive saving.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use evdev::KeyCode;
+use toml_edit::{value, ArrayOfTables, DocumentMut, Item, Table, TableLike};
+
+use super::{Config, BASE_LAYER};
+use crate::keys;
+
+/// Fields of a `[[mappings]]` entry that only make sense for dual-role keys.
+const TAP_HOLD_FIELDS: &[&str] = &[
+	"tap",
+	"hold",
+	"tapping_term",
+	"permissive_hold",
+	"hold_on_other_key_press",
+];
+
+pub struct ConfigEditor {
+	path: PathBuf,
+	/// The file as last read or written.
+	saved: String,
+	document: DocumentMut,
+}
+
+/// One line of [`ConfigEditor::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+	Same(String),
+	Added(String),
+	Removed(String),
+}
+
+impl ConfigEditor {
+	/// Opens a config for editing. Invalid configs are refused with the
+	/// same errors as [`Config::load`].
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let path = path.as_ref();
+		Config::load(path)?;
+		let saved = fs::read_to_string(path)
+			.with_context(|| format!("Cannot read config file {}", path.display()))?;
+		let document = saved
+			.parse::<DocumentMut>()
+			.with_context(|| format!("Invalid config {}", path.display()))?;
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			saved,
+			document,
+		})
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// The config with the edits made so far.
+	pub fn config(&self) -> Result<Config> {
+		self
+			.document
+			.to_string()
+			.parse()
+			.context("The edited config is invalid")
+	}
+
+	pub fn is_modified(&self) -> bool {
+		self.document.to_string() != self.saved
+	}
+
+	/// Makes `from` send `to` on `layer`, replacing what it did there
+	/// before. Mapping a key to itself on the base layer removes its
+	/// mapping; on other layers it stops the key falling through.
+	pub fn remap(&mut self, layer: &str, from: KeyCode, to: KeyCode) -> Result<()> {
+		if layer != BASE_LAYER {
+			let table = self.layer(layer)?;
+			let key = layer_key(table, from).unwrap_or_else(|| keys::name_of(from));
+			table.insert(&key, value(keys::name_of(to)));
+			return Ok(());
+		}
+		if from == to {
+			self.unmap(layer, from)?;
+			return Ok(());
+		}
+
+		let mappings = self.mappings()?;
+		let found = mappings.iter().position(|table| maps(table, from));
+		match found {
+			Some(idx) => {
+				let table = mappings.get_mut(idx).expect("position is in range");
+				for field in TAP_HOLD_FIELDS {
+					table.remove(field);
+				}
+				table["target_key"] = value(keys::name_of(to));
+			}
+			None => {
+				let mut table = Table::new();
+				table["original_key"] = value(keys::name_of(from));
+				table["target_key"] = value(keys::name_of(to));
+				mappings.push(table);
+			}
+		}
+		Ok(())
+	}
+
+	/// Drops the mapping of `from` on `layer`, letting it fall through to
+	/// the layers below. Returns whether there was one.
+	pub fn unmap(&mut self, layer: &str, from: KeyCode) -> Result<bool> {
+		if layer != BASE_LAYER {
+			let table = self.layer(layer)?;
+			let found = layer_key(table, from);
+			return Ok(found.is_some_and(|key| table.remove(&key).is_some()));
+		}
+		let mappings = self.mappings()?;
+		let before = mappings.len();
+		mappings.retain(|table| !maps(table, from));
+		Ok(mappings.len() != before)
+	}
+
+	/// The `[layers.<name>]` table, which must exist already: layers are
+	/// added in the file.
+	fn layer(&mut self, name: &str) -> Result<&mut dyn TableLike> {
+		self
+			.document
+			.get_mut("layers")
+			.and_then(Item::as_table_like_mut)
+			.and_then(|layers| layers.get_mut(name))
+			.and_then(Item::as_table_like_mut)
+			.with_context(|| format!("No layer named `{}`", name))
+	}
+
+	/// `[[mappings]]`, created on first use. An inline `mappings = [...]`
+	/// array is turned into tables so entries can be added alike.
+	fn mappings(&mut self) -> Result<&mut ArrayOfTables> {
+		let item = self.document.entry("mappings").or_insert(Item::None);
+		if item.is_none() {
+			*item = Item::ArrayOfTables(ArrayOfTables::new());
+		}
+		if !item.is_array_of_tables() {
+			match std::mem::take(item).into_array_of_tables() {
+				Ok(tables) => *item = Item::ArrayOfTables(tables),
+				Err(original) => {
+					*item = original;
+					bail!("`mappings` is not a list of tables");
+				}
+			}
+		}
+		Ok(item.as_array_of_tables_mut().expect("converted above"))
+	}
+
+	/// Line by line changes from the file on disk to the edited config.
+	pub fn diff(&self) -> Vec<DiffLine> {
+		diff_lines(&self.saved, &self.document.to_string())
+	}
+
+	/// Writes the edits back. A config that no longer parses is not written.
+	pub fn save(&mut self) -> Result<()> {
+		self.config()?;
+		let text = self.document.to_string();
+		// Write aside and rename, so a crash never leaves half a config.
+		// Through symlinks, so a linked config stays linked.
+		let path = fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+		let mut partial = path.clone().into_os_string();
+		partial.push(".partial");
+		let partial = PathBuf::from(partial);
+		fs::write(&partial, &text)
+			.with_context(|| format!("Cannot write config file {}", partial.display()))?;
+		if let Ok(metadata) = fs::metadata(&path) {
+			fs::set_permissions(&partial, metadata.permissions())
+				.with_context(|| format!("Cannot write config file {}", partial.display()))?;
+		}
+		fs::rename(&partial, &path)
+			.with_context(|| format!("Cannot write config file {}", path.display()))?;
+		self.saved = text;
+		Ok(())
+	}
+}
+
+/// Whether a `[[mappings]]` entry is for `code`, whatever alias it uses.
+fn maps(table: &Table, code: KeyCode) -> bool {
+	table
+		.get("original_key")
+		.and_then(Item::as_str)
+		.and_then(|name| keys::parse_key(name).ok())
+		== Some(code)
+}
+
+/// The key a layer table maps `code` under, whatever alias it uses.
+fn layer_key(table: &dyn TableLike, code: KeyCode) -> Option<String> {
+	table
+		.iter()
+		.find(|(key, _)| keys::parse_key(key).ok() == Some(code))
+		.map(|(key, _)| key.to_string())
+}
+
+/// Diffs two texts by their longest common subsequence of lines. Configs
+/// are short enough for the quadratic table.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+	let old: Vec<&str> = old.lines().collect();
+	let new: Vec<&str> = new.lines().collect();
+
+	// common[i][j]: length of the LCS of old[i..] and new[j..]
+	let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			common[i][j] = if old[i] == new[j] {
+				common[i + 1][j + 1] + 1
+			} else {
+				common[i + 1][j].max(common[i][j + 1])
+			};
+		}
+	}
+
+	let mut lines = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() || j < new.len() {
+		if i < old.len() && j < new.len() && old[i] == new[j] {
+			lines.push(DiffLine::Same(old[i].to_string()));
+			i += 1;
+			j += 1;
+		} else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+			lines.push(DiffLine::Removed(old[i].to_string()));
+			i += 1;
+		} else {
+			lines.push(DiffLine::Added(new[j].to_string()));
+			j += 1;
+		}
+	}
+	lines
+}
+
+#[cfg(test)]
+mod tests {
+	use tempfile::NamedTempFile;
+
+	use super::*;
+	use crate::config::{Layer, Mapping};
+
+	fn editor(source: &str) -> (ConfigEditor, NamedTempFile) {
+		let file = NamedTempFile::new().unwrap();
+		fs::write(file.path(), source).unwrap();
+		(ConfigEditor::open(file.path()).unwrap(), file)
+	}
+
+	#[test]
+	fn remaps_and_keeps_comments() {
+		let (mut editor, file) = editor(
+			"# My keys\nname = \"x\"\n\n[[mappings]]\noriginal_key = \"Caps\" # the big one\ntap = \"Esc\"\nhold = \"LCtrl\"\n",
+		);
+		editor
+			.remap(BASE_LAYER, KeyCode::KEY_CAPSLOCK, KeyCode::KEY_ESC)
+			.unwrap();
+		editor
+			.remap(BASE_LAYER, KeyCode::KEY_RIGHTALT, KeyCode::KEY_F13)
+			.unwrap();
+		assert!(editor.is_modified());
+
+		let config = editor.config().unwrap();
+		assert_eq!(
+			config.mappings,
+			vec![
+				Mapping::key("Caps", "Escape"),
+				Mapping::key("RightAlt", "F13")
+			]
+		);
+
+		editor.save().unwrap();
+		let saved = fs::read_to_string(file.path()).unwrap();
+		assert!(saved.starts_with("# My keys\n"));
+		assert!(saved.contains("original_key = \"Caps\" # the big one"));
+		assert!(!editor.is_modified());
+	}
+
+	#[test]
+	fn unmaps_and_diffs() {
+		let (mut editor, _file) = editor(
+			"name = \"x\"\nmappings = [{ original_key = \"A\", target_key = \"B\" }, { original_key = \"C\", target_key = \"D\" }]\n",
+		);
+
+		assert!(editor.unmap(BASE_LAYER, KeyCode::KEY_A).unwrap());
+		assert!(!editor.unmap(BASE_LAYER, KeyCode::KEY_A).unwrap());
+		// Mapping a key to itself is the same as removing its mapping
+		editor
+			.remap(BASE_LAYER, KeyCode::KEY_C, KeyCode::KEY_C)
+			.unwrap();
+		assert!(editor.config().unwrap().mappings.is_empty());
+
+		let diff = editor.diff();
+		assert_eq!(diff[0], DiffLine::Same("name = \"x\"".to_string()));
+		assert!(diff[1..]
+			.iter()
+			.any(|line| matches!(line, DiffLine::Removed(line) if line.starts_with("mappings = "))));
+	}
+
+	#[test]
+	fn edits_other_layers() {
+		let (mut editor, _file) =
+			editor("name = \"x\"\n\n[layers.nav]\nh = \"Left\" # vim\nj = \"Down\"\n");
+
+		editor
+			.remap("nav", KeyCode::KEY_H, KeyCode::KEY_HOME)
+			.unwrap();
+		editor
+			.remap("nav", KeyCode::KEY_K, KeyCode::KEY_UP)
+			.unwrap();
+		assert!(editor.unmap("nav", KeyCode::KEY_J).unwrap());
+		assert!(!editor.unmap("nav", KeyCode::KEY_J).unwrap());
+		assert!(editor.config().unwrap().mappings.is_empty());
+		assert_eq!(
+			editor.config().unwrap().layers["nav"],
+			Layer::from([
+				("h".to_string(), "Home".to_string()),
+				("K".to_string(), "Up".to_string()),
+			])
+		);
+		assert!(editor
+			.remap("fn", KeyCode::KEY_H, KeyCode::KEY_HOME)
+			.is_err());
+	}
+
+	#[test]
+	fn diffs_lines() {
+		assert_eq!(
+			diff_lines("a\nb\nc\nd\n", "a\nc\nD\n"),
+			vec![
+				DiffLine::Same("a".to_string()),
+				DiffLine::Removed("b".to_string()),
+				DiffLine::Same("c".to_string()),
+				DiffLine::Removed("d".to_string()),
+				DiffLine::Added("D".to_string()),
+			]
+		);
+	}
+}
@@ -0,0 +1,574 @@
+//! Static checks of a config for `validate`: mistakes that stop it from
+//! loading are errors, ones that load but are likely not what was meant
+//! are warnings. Unlike loading, every problem is reported, not the first.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use evdev::KeyCode;
+use serde::Serialize;
+
+use super::{Action, Config, MacroStep, PanicChord, BASE_LAYER};
+use crate::keys;
+use crate::remap::{is_transparent, layer_names, parse_target, Keymap, LayerId, Target};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	Warning,
+	Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	/// Names the check, e.g. `unknown-key`.
+	pub code: &'static str,
+	/// Where in the config, e.g. `mappings[2].target_key`.
+	pub field: String,
+	pub message: String,
+}
+
+impl Diagnostic {
+	fn error<F: Into<String>, M: Into<String>>(code: &'static str, field: F, message: M) -> Self {
+		Self {
+			severity: Severity::Error,
+			code,
+			field: field.into(),
+			message: message.into(),
+		}
+	}
+
+	fn warning<F: Into<String>, M: Into<String>>(code: &'static str, field: F, message: M) -> Self {
+		Self {
+			severity: Severity::Warning,
+			..Self::error(code, field, message)
+		}
+	}
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let severity = match self.severity {
+			Severity::Warning => "warning",
+			Severity::Error => "error",
+		};
+		if self.field.is_empty() {
+			write!(f, "{}[{}]: {}", severity, self.code, self.message)
+		} else {
+			write!(
+				f,
+				"{}[{}] {}: {}",
+				severity, self.code, self.field, self.message
+			)
+		}
+	}
+}
+
+/// Every problem found in `config`, errors first.
+pub fn lint(config: &Config) -> Vec<Diagnostic> {
+	let mut linter = Linter {
+		layers: layer_names(config),
+		macros: config.macros.keys().cloned().collect(),
+		diagnostics: Vec::new(),
+	};
+	linter.check_mappings(config);
+	linter.check_layers(config);
+	linter.check_combos(config);
+	linter.check_macros(config);
+	linter.check_panic(&config.panic.clone().unwrap_or_default());
+
+	// Anything loading still refuses that the checks above missed
+	let errors = linter
+		.diagnostics
+		.iter()
+		.any(|diagnostic| diagnostic.severity == Severity::Error);
+	if !errors {
+		if let Err(err) = Keymap::from_config(config) {
+			linter
+				.diagnostics
+				.push(Diagnostic::error("invalid", "", format!("{:#}", err)));
+		}
+	}
+
+	let mut diagnostics = linter.diagnostics;
+	diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity));
+	diagnostics
+}
+
+struct Linter {
+	layers: Vec<String>,
+	macros: Vec<String>,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+	fn key(&mut self, field: &str, name: &str) -> Option<KeyCode> {
+		match keys::parse_key(name) {
+			Ok(code) => Some(code),
+			Err(err) => {
+				self
+					.diagnostics
+					.push(Diagnostic::error("unknown-key", field, err.to_string()));
+				None
+			}
+		}
+	}
+
+	fn target(&mut self, field: &str, spec: &str) -> Option<Target> {
+		match parse_target(spec, &self.layers, &self.macros) {
+			Ok(target) => Some(target),
+			Err(err) => {
+				// Layer switches and macros have their own mistakes
+				let code = if spec.contains('(') {
+					"invalid-target"
+				} else {
+					"unknown-key"
+				};
+				self
+					.diagnostics
+					.push(Diagnostic::error(code, field, format!("{:#}", err)));
+				None
+			}
+		}
+	}
+
+	fn check_mappings(&mut self, config: &Config) {
+		let mut mapped: HashMap<KeyCode, usize> = HashMap::new();
+		// Plain key-to-key remaps, for chains and cycles
+		let mut remaps: HashMap<KeyCode, (usize, KeyCode)> = HashMap::new();
+
+		for (idx, mapping) in config.mappings.iter().enumerate() {
+			let field = |name: &str| format!("mappings[{}].{}", idx, name);
+			let original = self.key(&field("original_key"), &mapping.original_key);
+			let targets = match &mapping.action {
+				Action::Key(spec) => vec![self.target(&field("target_key"), spec)],
+				Action::TapHold(tap_hold) => vec![
+					self.target(&field("tap"), &tap_hold.tap),
+					self.target(&field("hold"), &tap_hold.hold),
+				],
+			};
+			let Some(original) = original else {
+				continue;
+			};
+
+			if let Some(first) = mapped.insert(original, idx) {
+				self.diagnostics.push(Diagnostic::error(
+					"duplicate-mapping",
+					field("original_key"),
+					format!(
+						"`{}` is already mapped by mappings[{}], only one of them can apply",
+						keys::name_of(original),
+						first
+					),
+				));
+			}
+			if let [Some(Target::Key(target))] = targets[..] {
+				remaps.insert(original, (idx, target));
+			}
+		}
+
+		self.check_remaps(&remaps);
+	}
+
+	/// Mappings apply once, never to the output of another mapping: a swap
+	/// or a longer rotation works as written, while `A → B` next to
+	/// `B → C` still sends B for A, which is seldom what was meant.
+	fn check_remaps(&mut self, remaps: &HashMap<KeyCode, (usize, KeyCode)>) {
+		let mut sorted: Vec<(&KeyCode, &(usize, KeyCode))> = remaps.iter().collect();
+		sorted.sort_by_key(|(_, (idx, _))| *idx);
+
+		let mut in_cycle = BTreeSet::new();
+		for &(&from, &(idx, to)) in &sorted {
+			let field = format!("mappings[{}].target_key", idx);
+			if from == to {
+				self.diagnostics.push(Diagnostic::warning(
+					"self-mapping",
+					field,
+					format!("`{}` is mapped to itself", keys::name_of(from)),
+				));
+				continue;
+			}
+			if in_cycle.contains(&idx) {
+				continue;
+			}
+
+			// Follow the remaps from `to` for as long as they go
+			let mut path = vec![from, to];
+			let mut indices = vec![idx];
+			let mut current = to;
+			while let Some(&(next_idx, next)) = remaps.get(&current) {
+				if next != from && path.contains(&next) {
+					break;
+				}
+				indices.push(next_idx);
+				path.push(next);
+				if next == from {
+					break;
+				}
+				current = next;
+			}
+			let names: Vec<String> = path.iter().map(|code| keys::name_of(*code)).collect();
+
+			if path.len() > 2 && path.last() == Some(&from) {
+				in_cycle.extend(indices);
+				// A swap is the common, intended case
+				if path.len() > 3 {
+					self.diagnostics.push(Diagnostic::warning(
+						"mapping-cycle",
+						field,
+						format!(
+							"{} rotates {} keys, each sends the next one once; check this is not meant to be a swap",
+							names.join(" → "),
+							path.len() - 1
+						),
+					));
+				}
+			} else if let Some(&(_, next)) = remaps.get(&to) {
+				self.diagnostics.push(Diagnostic::warning(
+					"mapping-chain",
+					field,
+					format!(
+						"`{}` sends `{}`, which is itself mapped to `{}`; mappings do not chain, so it still sends `{}`",
+						names[0],
+						names[1],
+						keys::name_of(next),
+						names[1]
+					),
+				));
+			}
+		}
+	}
+
+	fn check_layers(&mut self, config: &Config) {
+		if config.layers.contains_key(BASE_LAYER) {
+			self.diagnostics.push(Diagnostic::error(
+				"reserved-layer",
+				format!("layers.{}", BASE_LAYER),
+				format!("`{}` is reserved for the [[mappings]] list", BASE_LAYER),
+			));
+		}
+
+		// Which layers each layer can switch to, combos counting as base
+		let mut switches: Vec<Vec<LayerId>> = vec![Vec::new(); self.layers.len()];
+		let layer_targets = |target: Option<Target>| match target {
+			Some(Target::Layer(switch)) => Some(switch.layer),
+			_ => None,
+		};
+		for mapping in &config.mappings {
+			let specs = match &mapping.action {
+				Action::Key(spec) => vec![spec],
+				Action::TapHold(tap_hold) => vec![&tap_hold.tap, &tap_hold.hold],
+			};
+			for spec in specs {
+				let target = parse_target(spec, &self.layers, &self.macros).ok();
+				switches[0].extend(layer_targets(target));
+			}
+		}
+		for combo in &config.combos {
+			let target = parse_target(&combo.target, &self.layers, &self.macros).ok();
+			switches[0].extend(layer_targets(target));
+		}
+
+		for (layer, (name, entries)) in config.layers.iter().enumerate() {
+			let id = layer + 1;
+			let mut mapped: HashMap<KeyCode, &str> = HashMap::new();
+			for (key, spec) in entries {
+				let field = format!("layers.{}.{}", name, key);
+				if let Some(code) = self.key(&field, key) {
+					if let Some(first) = mapped.insert(code, key) {
+						self.diagnostics.push(Diagnostic::error(
+							"duplicate-mapping",
+							&field,
+							format!(
+								"`{}` is already mapped by layers.{}.{}, only one of them can apply",
+								keys::name_of(code),
+								name,
+								first
+							),
+						));
+					}
+				}
+				if is_transparent(spec) {
+					continue;
+				}
+				let target = self.target(&field, spec);
+				switches[id].extend(layer_targets(target));
+			}
+		}
+
+		let mut reached = vec![false; self.layers.len()];
+		let mut queue = vec![0];
+		while let Some(layer) = queue.pop() {
+			if std::mem::replace(&mut reached[layer], true) {
+				continue;
+			}
+			queue.extend(switches[layer].iter().copied());
+		}
+		for (name, _) in config
+			.layers
+			.keys()
+			.zip(&reached[1..])
+			.filter(|(_, reached)| !**reached)
+		{
+			self.diagnostics.push(Diagnostic::warning(
+				"unreachable-layer",
+				format!("layers.{}", name),
+				format!(
+					"no key switches to `{}`, add e.g. `MO({})` to a key of a layer that is reachable",
+					name, name
+				),
+			));
+		}
+	}
+
+	fn check_combos(&mut self, config: &Config) {
+		let tap_holds: HashMap<KeyCode, usize> = config
+			.mappings
+			.iter()
+			.enumerate()
+			.filter(|(_, mapping)| matches!(mapping.action, Action::TapHold(_)))
+			.filter_map(|(idx, mapping)| Some((keys::parse_key(&mapping.original_key).ok()?, idx)))
+			.collect();
+
+		for (idx, combo) in config.combos.iter().enumerate() {
+			let field = |name: &str| format!("combos[{}].{}", idx, name);
+			self.target(&field("target"), &combo.target);
+			if combo.keys.len() < 2 {
+				self.diagnostics.push(Diagnostic::error(
+					"invalid-combo",
+					field("keys"),
+					"a combo needs at least two keys",
+				));
+			}
+
+			let mut codes = Vec::new();
+			for key in &combo.keys {
+				let Some(code) = self.key(&field("keys"), key) else {
+					continue;
+				};
+				if codes.contains(&code) {
+					self.diagnostics.push(Diagnostic::error(
+						"invalid-combo",
+						field("keys"),
+						format!("`{}` is listed twice", key),
+					));
+				}
+				codes.push(code);
+
+				if let Some(mapping) = tap_holds.get(&code) {
+					self.diagnostics.push(Diagnostic::warning(
+						"combo-tap-hold",
+						field("keys"),
+						format!(
+							"`{}` is also a dual-role key (mappings[{}]); the combo holds its presses back first, so its tap or hold is decided late",
+							keys::name_of(code),
+							mapping
+						),
+					));
+				}
+			}
+		}
+	}
+
+	fn check_macros(&mut self, config: &Config) {
+		for (name, steps) in &config.macros {
+			for (idx, step) in steps.iter().enumerate() {
+				let field = format!("macros.{}[{}]", name, idx);
+				match step {
+					MacroStep::Keys(spec) => {
+						for part in spec.split('+').filter(|part| !part.trim().is_empty()) {
+							self.key(&field, part);
+						}
+					}
+					MacroStep::Text { text } => {
+						for ch in text.chars().filter(|ch| keys::from_char(*ch).is_none()) {
+							self.diagnostics.push(Diagnostic::error(
+								"unknown-key",
+								field.clone(),
+								format!("cannot type {:?} on a US layout", ch),
+							));
+						}
+					}
+					MacroStep::Delay { .. } => {}
+				}
+			}
+		}
+	}
+
+	/// The chord is the way out when a mapping goes wrong, so it had better
+	/// be able to fire.
+	fn check_panic(&mut self, chord: &PanicChord) {
+		if chord.keys.is_empty() {
+			self.diagnostics.push(Diagnostic::error(
+				"panic-chord",
+				"panic.keys",
+				"the panic chord needs at least one key, or nothing stops a broken mapping",
+			));
+			return;
+		}
+		let mut codes = Vec::new();
+		for key in &chord.keys {
+			let Some(code) = self.key("panic.keys", key) else {
+				continue;
+			};
+			if codes.contains(&code) {
+				self.diagnostics.push(Diagnostic::error(
+					"panic-chord",
+					"panic.keys",
+					format!(
+						"`{}` is listed twice, so the chord can never be completed",
+						key
+					),
+				));
+			}
+			codes.push(code);
+		}
+		if chord.keys.len() == 1 {
+			self.diagnostics.push(Diagnostic::warning(
+				"panic-chord",
+				"panic.keys",
+				"holding a single key stops remapping, which is easy to do by accident",
+			));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn codes(config: &str) -> Vec<(Severity, &'static str, String)> {
+		let config: Config = config.parse().unwrap();
+		lint(&config)
+			.into_iter()
+			.map(|diagnostic| (diagnostic.severity, diagnostic.code, diagnostic.field))
+			.collect()
+	}
+
+	#[test]
+	fn accepts_the_sample_config() {
+		let config: Config = include_str!("../../sampleConfig.toml").parse().unwrap();
+		assert_eq!(lint(&config), []);
+	}
+
+	#[test]
+	fn reports_every_problem() {
+		let found = codes(
+			r#"
+name = "x"
+
+[[mappings]]
+original_key = "CapsLock"
+target_key = "Escap"
+
+[[mappings]]
+original_key = "caps"
+tap = "Esc"
+hold = "LCtrl"
+
+[[mappings]]
+original_key = "J"
+tap = "J"
+hold = "MO(nav)"
+
+[[combos]]
+keys = ["J", "K"]
+target = "Escape"
+
+[layers.nav]
+H = "Left"
+h = "Home"
+
+[layers.numpad]
+J = "Kp4"
+
+[panic]
+keys = ["Escape", "Esc"]
+"#,
+		);
+		assert_eq!(
+			found,
+			[
+				(
+					Severity::Error,
+					"unknown-key",
+					"mappings[0].target_key".to_string()
+				),
+				(
+					Severity::Error,
+					"duplicate-mapping",
+					"mappings[1].original_key".to_string()
+				),
+				(
+					Severity::Error,
+					"duplicate-mapping",
+					"layers.nav.h".to_string()
+				),
+				(Severity::Error, "panic-chord", "panic.keys".to_string()),
+				(
+					Severity::Warning,
+					"unreachable-layer",
+					"layers.numpad".to_string()
+				),
+				(
+					Severity::Warning,
+					"combo-tap-hold",
+					"combos[0].keys".to_string()
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn tells_swaps_from_chains() {
+		let found = codes(
+			r#"
+name = "x"
+
+[[mappings]]
+original_key = "CapsLock"
+target_key = "Escape"
+
+[[mappings]]
+original_key = "Escape"
+target_key = "CapsLock"
+
+[[mappings]]
+original_key = "A"
+target_key = "B"
+
+[[mappings]]
+original_key = "B"
+target_key = "C"
+
+[[mappings]]
+original_key = "X"
+target_key = "Y"
+
+[[mappings]]
+original_key = "Y"
+target_key = "Z"
+
+[[mappings]]
+original_key = "Z"
+target_key = "X"
+"#,
+		);
+		assert_eq!(
+			found,
+			[
+				(
+					Severity::Warning,
+					"mapping-chain",
+					"mappings[2].target_key".to_string()
+				),
+				(
+					Severity::Warning,
+					"mapping-cycle",
+					"mappings[4].target_key".to_string()
+				),
+			]
+		);
+	}
+}
@@ -0,0 +1,488 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::device::DeviceInfo;
+
+pub mod edit;
+pub mod lint;
+
+/// A keyboard layout as described by a geekCaps TOML file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+	pub name: String,
+	/// Device to apply mappings to, the first keyboard found when unset.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub device: Option<DeviceSelector>,
+	/// Default tapping term for dual-role keys, in milliseconds.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub tapping_term: Option<u64>,
+	#[serde(default)]
+	pub mappings: Vec<Mapping>,
+	/// Default window for pressing all keys of a combo, in milliseconds.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub combo_term: Option<u64>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub combos: Vec<Combo>,
+	/// `[layers.<name>]` tables stacked on top of the base `mappings`.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub layers: BTreeMap<String, Layer>,
+	/// Named key sequences, triggered by the `MACRO(name)` target.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub macros: BTreeMap<String, Macro>,
+	/// Keys that stop remapping when held together, [`PanicChord::default`]
+	/// when unset.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub panic: Option<PanicChord>,
+	/// Counts presses while remapping, for `stats` and the heatmap. Nothing
+	/// is counted when unset.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub stats: Option<StatsConfig>,
+}
+
+/// Where and what the daemon counts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatsConfig {
+	/// The stats file, [`Stats::default_path`](crate::stats::Stats::default_path)
+	/// when unset.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub file: Option<PathBuf>,
+	/// Also count pairs of keys pressed one after the other. Off unless
+	/// asked for, as they hint at what was typed.
+	#[serde(default, skip_serializing_if = "is_false")]
+	pub bigrams: bool,
+}
+
+/// Physical keys that, held together for `hold` milliseconds, make the
+/// daemon release the keyboard and exit whatever the mappings say.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PanicChord {
+	pub keys: Vec<String>,
+	#[serde(default = "default_panic_hold")]
+	pub hold: u64,
+}
+
+/// How long the panic chord must be held, in milliseconds.
+pub const DEFAULT_PANIC_HOLD_MS: u64 = 2000;
+
+fn default_panic_hold() -> u64 {
+	DEFAULT_PANIC_HOLD_MS
+}
+
+impl Default for PanicChord {
+	/// Both Shift keys and Escape.
+	fn default() -> Self {
+		Self {
+			keys: vec![
+				"LeftShift".to_string(),
+				"RightShift".to_string(),
+				"Escape".to_string(),
+			],
+			hold: DEFAULT_PANIC_HOLD_MS,
+		}
+	}
+}
+
+/// Used when neither the combo nor the config sets a combo term.
+pub const DEFAULT_COMBO_TERM_MS: u64 = 50;
+
+/// Keys that produce `target` when all pressed together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Combo {
+	pub keys: Vec<String>,
+	pub target: String,
+	/// Milliseconds, overriding the config-wide `combo_term`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub timeout: Option<u64>,
+}
+
+/// Name of the implicit layer formed by `[[mappings]]`.
+pub const BASE_LAYER: &str = "base";
+
+/// A layer maps physical key names to targets. Keys a layer leaves out, or
+/// maps to `"_"`, are transparent and fall through to the layers below.
+///
+/// Besides key names, targets may switch layers: `MO(nav)` while held,
+/// `TG(nav)` toggles, `OSL(nav)` for the next key only and `TO(nav)` makes
+/// `nav` the only active layer (`TO(base)` clears them all).
+pub type Layer = BTreeMap<String, String>;
+
+/// Steps a macro plays back in order.
+pub type Macro = Vec<MacroStep>;
+
+/// One step of a [`Macro`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MacroStep {
+	/// `"Ctrl+Shift+T"`: tap the last key while holding the ones before it.
+	Keys(String),
+	/// `{ text = "git status" }`: type text as on a US layout.
+	Text { text: String },
+	/// `{ delay = 100 }`: wait this many milliseconds.
+	Delay { delay: u64 },
+}
+
+/// Used when neither the mapping nor the config sets a tapping term.
+pub const DEFAULT_TAPPING_TERM_MS: u64 = 200;
+
+/// What one physical key does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawMapping", into = "RawMapping")]
+pub struct Mapping {
+	pub original_key: String,
+	pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+	/// `target_key = "Escape"`
+	Key(String),
+	/// `tap = "Escape"` and `hold = "LeftCtrl"`
+	TapHold(TapHold),
+}
+
+/// A dual-role key: `tap` when pressed and released quickly, `hold` when
+/// held past the tapping term or, depending on the flags, used together
+/// with another key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapHold {
+	pub tap: String,
+	pub hold: String,
+	/// Milliseconds, overriding the config-wide `tapping_term`.
+	pub tapping_term: Option<u64>,
+	/// Choose hold as soon as another key is pressed and released while
+	/// this key is down.
+	pub permissive_hold: bool,
+	/// Choose hold as soon as another key is pressed while this key is down.
+	pub hold_on_other_key_press: bool,
+}
+
+impl Mapping {
+	/// A plain `original_key → target_key` remap.
+	pub fn key<S: Into<String>, T: Into<String>>(original_key: S, target_key: T) -> Self {
+		Self {
+			original_key: original_key.into(),
+			action: Action::Key(target_key.into()),
+		}
+	}
+}
+
+/// The on-disk shape of a `[[mappings]]` entry; which fields are present
+/// decides the [`Action`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMapping {
+	original_key: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	target_key: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	tap: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	hold: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	tapping_term: Option<u64>,
+	#[serde(default, skip_serializing_if = "is_false")]
+	permissive_hold: bool,
+	#[serde(default, skip_serializing_if = "is_false")]
+	hold_on_other_key_press: bool,
+}
+
+fn is_false(flag: &bool) -> bool {
+	!flag
+}
+
+impl TryFrom<RawMapping> for Mapping {
+	type Error = String;
+
+	fn try_from(raw: RawMapping) -> Result<Self, Self::Error> {
+		let action = match (raw.target_key, raw.tap, raw.hold) {
+			(Some(target_key), None, None) => {
+				if raw.tapping_term.is_some() || raw.permissive_hold || raw.hold_on_other_key_press {
+					return Err(format!(
+						"mapping for `{}` sets tap/hold options without `tap` and `hold`",
+						raw.original_key
+					));
+				}
+				Action::Key(target_key)
+			}
+			(None, Some(tap), Some(hold)) => Action::TapHold(TapHold {
+				tap,
+				hold,
+				tapping_term: raw.tapping_term,
+				permissive_hold: raw.permissive_hold,
+				hold_on_other_key_press: raw.hold_on_other_key_press,
+			}),
+			_ => {
+				return Err(format!(
+					"mapping for `{}` needs either `target_key` or both `tap` and `hold`",
+					raw.original_key
+				))
+			}
+		};
+
+		Ok(Self {
+			original_key: raw.original_key,
+			action,
+		})
+	}
+}
+
+impl From<Mapping> for RawMapping {
+	fn from(mapping: Mapping) -> Self {
+		let mut raw = RawMapping {
+			original_key: mapping.original_key,
+			..RawMapping::default()
+		};
+		match mapping.action {
+			Action::Key(target_key) => raw.target_key = Some(target_key),
+			Action::TapHold(tap_hold) => {
+				raw.tap = Some(tap_hold.tap);
+				raw.hold = Some(tap_hold.hold);
+				raw.tapping_term = tap_hold.tapping_term;
+				raw.permissive_hold = tap_hold.permissive_hold;
+				raw.hold_on_other_key_press = tap_hold.hold_on_other_key_press;
+			}
+		}
+		raw
+	}
+}
+
+/// Picks the input device a config applies to, either by name
+/// (`device = "AT Translated Set 2 keyboard"`) or by a table of criteria
+/// that must all match (`device = { vendor = 0x046d, product = 0xc52b }`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceSelector {
+	Name(String),
+	Match(DeviceMatch),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceMatch {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub path: Option<PathBuf>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub phys: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub vendor: Option<u16>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub product: Option<u16>,
+}
+
+impl DeviceSelector {
+	pub fn matches(&self, device: &DeviceInfo) -> bool {
+		match self {
+			DeviceSelector::Name(name) => device.name == *name,
+			DeviceSelector::Match(criteria) => {
+				criteria
+					.name
+					.as_ref()
+					.is_none_or(|name| device.name == *name)
+					&& criteria
+						.path
+						.as_ref()
+						.is_none_or(|path| device.path == *path)
+					&& criteria
+						.phys
+						.as_ref()
+						.is_none_or(|phys| device.phys.as_ref() == Some(phys))
+					&& criteria.vendor.is_none_or(|vendor| device.vendor == vendor)
+					&& criteria
+						.product
+						.is_none_or(|product| device.product == product)
+			}
+		}
+	}
+}
+
+impl fmt::Display for DeviceSelector {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DeviceSelector::Name(name) => write!(f, "\"{}\"", name),
+			DeviceSelector::Match(criteria) => {
+				let mut parts = Vec::new();
+				if let Some(name) = &criteria.name {
+					parts.push(format!("name = \"{}\"", name));
+				}
+				if let Some(path) = &criteria.path {
+					parts.push(format!("path = \"{}\"", path.display()));
+				}
+				if let Some(phys) = &criteria.phys {
+					parts.push(format!("phys = \"{}\"", phys));
+				}
+				if let Some(vendor) = criteria.vendor {
+					parts.push(format!("vendor = {:#06x}", vendor));
+				}
+				if let Some(product) = criteria.product {
+					parts.push(format!("product = {:#06x}", product));
+				}
+				write!(f, "{{ {} }}", parts.join(", "))
+			}
+		}
+	}
+}
+
+impl Config {
+	/// Reads and parses a config file. Parse errors are reported as
+	/// `path:line:column` followed by the TOML error.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let path = path.as_ref();
+		let source = fs::read_to_string(path)
+			.with_context(|| format!("Cannot read config file {}", path.display()))?;
+
+		toml::from_str(&source).map_err(|err| {
+			let location = match err.span() {
+				Some(span) => {
+					let (line, column) = line_column(&source, span.start);
+					format!("{}:{}:{}", path.display(), line, column)
+				}
+				None => path.display().to_string(),
+			};
+			anyhow::Error::new(err).context(format!("Invalid config {}", location))
+		})
+	}
+
+	pub fn to_toml(&self) -> Result<String> {
+		toml::to_string_pretty(self).context("Cannot serialize config")
+	}
+}
+
+impl FromStr for Config {
+	type Err = toml::de::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		toml::from_str(s)
+	}
+}
+
+/// Converts a byte offset into a 1-based line and column.
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+	let before = &source[..offset.min(source.len())];
+	let line = before.matches('\n').count() + 1;
+	let column = before
+		.rfind('\n')
+		.map_or(before, |newline| &before[newline + 1..])
+		.chars()
+		.count()
+		+ 1;
+	(line, column)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_sample_config() {
+		let config: Config = include_str!("../../sampleConfig.toml").parse().unwrap();
+
+		assert_eq!(config.name, "Developer Keyboard Layout");
+		assert_eq!(config.device, None);
+		assert_eq!(config.mappings.len(), 3);
+		assert_eq!(config.mappings[2], Mapping::key("RightAlt", "F13"));
+	}
+
+	#[test]
+	fn round_trips() {
+		let config = Config {
+			name: "Round trip".to_string(),
+			device: Some(DeviceSelector::Match(DeviceMatch {
+				vendor: Some(0x046d),
+				product: Some(0xc52b),
+				..DeviceMatch::default()
+			})),
+			tapping_term: Some(180),
+			mappings: vec![
+				Mapping::key("Escape", "CapsLock"),
+				Mapping {
+					original_key: "CapsLock".to_string(),
+					action: Action::TapHold(TapHold {
+						tap: "Escape".to_string(),
+						hold: "LeftCtrl".to_string(),
+						tapping_term: None,
+						permissive_hold: true,
+						hold_on_other_key_press: false,
+					}),
+				},
+			],
+			combo_term: Some(40),
+			combos: vec![Combo {
+				keys: vec!["J".to_string(), "K".to_string()],
+				target: "Escape".to_string(),
+				timeout: None,
+			}],
+			layers: BTreeMap::from([(
+				"nav".to_string(),
+				Layer::from([
+					("H".to_string(), "Left".to_string()),
+					("Space".to_string(), "TO(base)".to_string()),
+				]),
+			)]),
+			macros: BTreeMap::from([(
+				"status".to_string(),
+				vec![
+					MacroStep::Text {
+						text: "git status".to_string(),
+					},
+					MacroStep::Delay { delay: 20 },
+					MacroStep::Keys("Enter".to_string()),
+				],
+			)]),
+			panic: Some(PanicChord {
+				keys: vec!["LeftCtrl".to_string(), "F12".to_string()],
+				hold: 1000,
+			}),
+			stats: Some(StatsConfig {
+				file: None,
+				bigrams: true,
+			}),
+		};
+
+		let parsed: Config = config.to_toml().unwrap().parse().unwrap();
+		assert_eq!(parsed, config);
+
+		let by_name: Config = "name = \"x\"\ndevice = \"Keychron K2\"".parse().unwrap();
+		assert_eq!(
+			by_name.device,
+			Some(DeviceSelector::Name("Keychron K2".to_string()))
+		);
+	}
+
+	#[test]
+	fn rejects_incomplete_tap_hold() {
+		let err = "name = \"x\"\n[[mappings]]\noriginal_key = \"CapsLock\"\ntap = \"Escape\"\n"
+			.parse::<Config>()
+			.unwrap_err();
+		assert!(err
+			.message()
+			.contains("needs either `target_key` or both `tap` and `hold`"));
+	}
+
+	#[test]
+	fn reports_error_location() {
+		let file = tempfile::NamedTempFile::new().unwrap();
+		fs::write(
+			file.path(),
+			"name = \"x\"\n\n[[mappings]]\noriginal_key = \"A\"\ntarget = \"B\"\n",
+		)
+		.unwrap();
+
+		let err = Config::load(file.path()).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			format!("Invalid config {}:5:1", file.path().display())
+		);
+	}
+}
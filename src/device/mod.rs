@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use evdev::{Device, EventType, KeyCode};
+use serde::Serialize;
+
+use crate::config::DeviceSelector;
+use crate::remap::VIRTUAL_KEYBOARD_NAME;
+
+/// Keys a device must report before we treat it as a full keyboard rather
+/// than a media remote, power button or mouse with a couple of extra keys.
+const ALPHANUMERIC_KEYS: [KeyCode; 36] = [
+	KeyCode::KEY_A,
+	KeyCode::KEY_B,
+	KeyCode::KEY_C,
+	KeyCode::KEY_D,
+	KeyCode::KEY_E,
+	KeyCode::KEY_F,
+	KeyCode::KEY_G,
+	KeyCode::KEY_H,
+	KeyCode::KEY_I,
+	KeyCode::KEY_J,
+	KeyCode::KEY_K,
+	KeyCode::KEY_L,
+	KeyCode::KEY_M,
+	KeyCode::KEY_N,
+	KeyCode::KEY_O,
+	KeyCode::KEY_P,
+	KeyCode::KEY_Q,
+	KeyCode::KEY_R,
+	KeyCode::KEY_S,
+	KeyCode::KEY_T,
+	KeyCode::KEY_U,
+	KeyCode::KEY_V,
+	KeyCode::KEY_W,
+	KeyCode::KEY_X,
+	KeyCode::KEY_Y,
+	KeyCode::KEY_Z,
+	KeyCode::KEY_0,
+	KeyCode::KEY_1,
+	KeyCode::KEY_2,
+	KeyCode::KEY_3,
+	KeyCode::KEY_4,
+	KeyCode::KEY_5,
+	KeyCode::KEY_6,
+	KeyCode::KEY_7,
+	KeyCode::KEY_8,
+	KeyCode::KEY_9,
+];
+
+/// A snapshot of one `/dev/input/event*` node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceInfo {
+	pub name: String,
+	pub path: PathBuf,
+	pub phys: Option<String>,
+	pub vendor: u16,
+	pub product: u16,
+	/// The device advertises `EV_KEY`.
+	pub has_keys: bool,
+	/// The device reports every letter and digit key.
+	pub alphanumeric: bool,
+}
+
+impl DeviceInfo {
+	pub fn from_device(path: PathBuf, device: &Device) -> Self {
+		let id = device.input_id();
+		let has_keys = device.supported_events().contains(EventType::KEY);
+		let alphanumeric = device
+			.supported_keys()
+			.is_some_and(|keys| ALPHANUMERIC_KEYS.iter().all(|key| keys.contains(*key)));
+
+		Self {
+			name: device.name().unwrap_or("Unknown").to_string(),
+			path,
+			phys: device.physical_path().map(str::to_string),
+			vendor: id.vendor(),
+			product: id.product(),
+			has_keys,
+			alphanumeric,
+		}
+	}
+
+	pub fn is_keyboard(&self) -> bool {
+		self.has_keys && self.alphanumeric
+	}
+}
+
+/// Source of input devices, so commands can be exercised against a fake
+/// device list instead of real hardware.
+pub trait DeviceProbe {
+	fn devices(&self) -> Result<Vec<DeviceInfo>>;
+}
+
+/// Probes the devices currently present under `/dev/input`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EvdevProbe;
+
+impl DeviceProbe for EvdevProbe {
+	fn devices(&self) -> Result<Vec<DeviceInfo>> {
+		let mut devices: Vec<DeviceInfo> = evdev::enumerate()
+			.map(|(path, device)| DeviceInfo::from_device(path, &device))
+			.collect();
+		devices.sort_by_key(|info| event_number(&info.path));
+		Ok(devices)
+	}
+}
+
+/// Finds the device a config applies to: the first one matching
+/// `selector`, or the first real keyboard when no selector is given.
+pub fn select(probe: &dyn DeviceProbe, selector: Option<&DeviceSelector>) -> Result<DeviceInfo> {
+	let devices = probe.devices()?;
+	let found = match selector {
+		Some(selector) => devices.into_iter().find(|device| selector.matches(device)),
+		None => devices
+			.into_iter()
+			.find(|device| device.is_keyboard() && device.name != VIRTUAL_KEYBOARD_NAME),
+	};
+
+	match (found, selector) {
+		(Some(device), _) => Ok(device),
+		(None, Some(selector)) => bail!(
+			"No input device matches {}, run `geekCaps list-devices` to see what is connected",
+			selector
+		),
+		(None, None) => bail!("No keyboard found, run `geekCaps list-devices` to check permissions"),
+	}
+}
+
+/// A fixed device list for tests.
+#[cfg(test)]
+pub(crate) struct FakeProbe(pub Vec<DeviceInfo>);
+
+#[cfg(test)]
+impl DeviceProbe for FakeProbe {
+	fn devices(&self) -> Result<Vec<DeviceInfo>> {
+		Ok(self.0.clone())
+	}
+}
+
+/// Sorts `event10` after `event9` instead of after `event1`.
+fn event_number(path: &std::path::Path) -> (u32, PathBuf) {
+	let number = path
+		.file_name()
+		.and_then(|name| name.to_str())
+		.and_then(|name| name.strip_prefix("event"))
+		.and_then(|n| n.parse().ok())
+		.unwrap_or(u32::MAX);
+	(number, path.to_path_buf())
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use evdev::KeyCode;
+use tuirealm::event::Key;
+
+mod table;
+
+pub use table::KEYS;
+
+/// One entry of the key registry: the name used in config files, the
+/// evdev code it stands for and how it is shown on a keycap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyDef {
+	/// Canonical config name, e.g. `CapsLock`.
+	pub name: &'static str,
+	pub aliases: &'static [&'static str],
+	pub code: KeyCode,
+	/// Short legend drawn on the TUI keycap.
+	pub label: &'static str,
+	/// Characters produced on a US layout without and with Shift.
+	pub chars: Option<(char, char)>,
+	term: Option<Key>,
+}
+
+impl KeyDef {
+	const fn new(
+		name: &'static str,
+		code: KeyCode,
+		label: &'static str,
+		aliases: &'static [&'static str],
+	) -> Self {
+		Self {
+			name,
+			aliases,
+			code,
+			label,
+			chars: None,
+			term: None,
+		}
+	}
+
+	const fn chars(mut self, plain: char, shifted: char) -> Self {
+		self.chars = Some((plain, shifted));
+		self
+	}
+
+	const fn term(mut self, key: Key) -> Self {
+		self.term = Some(key);
+		self
+	}
+
+	/// The key as crossterm reports it, if the terminal can see it at all.
+	pub fn term_key(&self) -> Option<Key> {
+		self.term.or(self.chars.map(|(plain, _)| Key::Char(plain)))
+	}
+}
+
+/// A key name that is neither in the registry nor a raw evdev `KEY_*` name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+	pub name: String,
+	pub suggestions: Vec<&'static str>,
+}
+
+impl fmt::Display for UnknownKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "unknown key `{}`", self.name)?;
+		match self.suggestions.as_slice() {
+			[] => Ok(()),
+			[only] => write!(f, ", did you mean `{}`?", only),
+			many => write!(
+				f,
+				", did you mean one of {}?",
+				many
+					.iter()
+					.map(|name| format!("`{}`", name))
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+		}
+	}
+}
+
+impl std::error::Error for UnknownKey {}
+
+static BY_NAME: LazyLock<HashMap<String, &'static KeyDef>> = LazyLock::new(|| {
+	let mut by_name = HashMap::new();
+	for def in KEYS {
+		for name in std::iter::once(&def.name).chain(def.aliases) {
+			by_name.entry(normalize(name)).or_insert(def);
+		}
+	}
+	by_name
+});
+
+/// Names compare case-insensitively and ignore `_`, `-` and spaces, so
+/// `caps_lock`, `Caps-Lock` and `CapsLock` are the same key. Single
+/// characters are kept as-is so `-` still names the minus key.
+fn normalize(name: &str) -> String {
+	let name = name.trim();
+	if name.chars().count() == 1 {
+		return name.to_lowercase();
+	}
+	name
+		.chars()
+		.filter(|c| !matches!(c, '_' | '-' | ' '))
+		.flat_map(char::to_lowercase)
+		.collect()
+}
+
+/// Looks a key up by its canonical name or any alias.
+pub fn lookup(name: &str) -> Option<&'static KeyDef> {
+	BY_NAME.get(&normalize(name)).copied()
+}
+
+/// Resolves a config key name to an evdev code. Besides registry names,
+/// raw evdev names such as `KEY_PROG1` are accepted for keys the registry
+/// does not cover.
+pub fn parse_key(name: &str) -> Result<KeyCode, UnknownKey> {
+	if let Some(def) = lookup(name) {
+		return Ok(def.code);
+	}
+	if let Ok(code) = KeyCode::from_str(name.trim()) {
+		return Ok(code);
+	}
+	Err(UnknownKey {
+		name: name.to_string(),
+		suggestions: suggest(name),
+	})
+}
+
+/// Registry names within a small edit distance of `name`, closest first.
+pub fn suggest(name: &str) -> Vec<&'static str> {
+	let wanted = normalize(name);
+	let max_distance = (wanted.chars().count() / 3).max(1);
+
+	let mut candidates: Vec<(usize, &'static str)> = BY_NAME
+		.iter()
+		.filter(|(known, _)| known.chars().count() > 1)
+		.map(|(known, def)| (strsim::levenshtein(&wanted, known), def.name))
+		.filter(|(distance, _)| *distance <= max_distance)
+		.collect();
+	candidates.sort();
+
+	let mut names = Vec::new();
+	for (_, name) in candidates {
+		if !names.contains(&name) {
+			names.push(name);
+		}
+	}
+	names.truncate(3);
+	names
+}
+
+pub fn from_code(code: KeyCode) -> Option<&'static KeyDef> {
+	KEYS.iter().find(|def| def.code == code)
+}
+
+/// Maps a key reported by crossterm back to the physical key. Shifted
+/// characters resolve to their base key (`!` is the `1` key) as on a US
+/// layout.
+pub fn from_term(key: Key) -> Option<&'static KeyDef> {
+	match key {
+		Key::BackTab => lookup("Tab"),
+		Key::Char(ch) => KEYS.iter().find(|def| {
+			def
+				.chars
+				.is_some_and(|(plain, shifted)| plain == ch || shifted == ch)
+		}),
+		key => KEYS.iter().find(|def| def.term == Some(key)),
+	}
+}
+
+/// The key a keycap labelled `label` stands for. `nth` picks among keys
+/// sharing a label, in registry order, so the second `Shift` cap on a row
+/// is the right Shift key.
+pub fn from_label(label: &str, nth: usize) -> Option<&'static KeyDef> {
+	let mut same = KEYS
+		.iter()
+		.filter(|def| def.label.eq_ignore_ascii_case(label));
+	same
+		.clone()
+		.nth(nth)
+		.or_else(|| same.next())
+		.or_else(|| lookup(label))
+}
+
+/// The key that types `ch` on a US layout and whether Shift is needed.
+/// Newlines and tabs type Enter and Tab.
+pub fn from_char(ch: char) -> Option<(&'static KeyDef, bool)> {
+	match ch {
+		'\n' => lookup("Enter").map(|def| (def, false)),
+		'\t' => lookup("Tab").map(|def| (def, false)),
+		ch => KEYS.iter().find_map(|def| match def.chars? {
+			(plain, _) if plain == ch => Some((def, false)),
+			(_, shifted) if shifted == ch => Some((def, true)),
+			_ => None,
+		}),
+	}
+}
+
+/// Display name for any evdev code, falling back to the raw `KEY_*` name.
+pub fn name_of(code: KeyCode) -> String {
+	from_code(code).map_or_else(|| format!("{:?}", code), |def| def.name.to_string())
+}
+
+/// Keycap label for any evdev code, falling back to [`name_of`].
+pub fn label_of(code: KeyCode) -> String {
+	from_code(code).map_or_else(|| name_of(code), |def| def.label.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolves_names_and_aliases() {
+		assert_eq!(parse_key("CapsLock"), Ok(KeyCode::KEY_CAPSLOCK));
+		assert_eq!(parse_key("caps_lock"), Ok(KeyCode::KEY_CAPSLOCK));
+		assert_eq!(parse_key("Esc"), parse_key("ESCAPE"));
+		assert_eq!(parse_key("LCtrl"), Ok(KeyCode::KEY_LEFTCTRL));
+		assert_eq!(parse_key("LeftCtrl"), Ok(KeyCode::KEY_LEFTCTRL));
+		assert_eq!(parse_key("F13"), Ok(KeyCode::KEY_F13));
+		assert_eq!(parse_key("-"), Ok(KeyCode::KEY_MINUS));
+		assert_eq!(parse_key("KEY_PROG1"), Ok(KeyCode::KEY_PROG1));
+	}
+
+	#[test]
+	fn suggests_close_names() {
+		let err = parse_key("Escpae").unwrap_err();
+		assert_eq!(err.suggestions.first(), Some(&"Escape"));
+		assert_eq!(
+			err.to_string(),
+			"unknown key `Escpae`, did you mean `Escape`?"
+		);
+
+		assert!(parse_key("Hyperdrive").unwrap_err().suggestions.is_empty());
+	}
+
+	#[test]
+	fn registry_names_are_unambiguous() {
+		let mut seen = HashMap::new();
+		for def in KEYS {
+			for name in std::iter::once(&def.name).chain(def.aliases) {
+				if let Some(other) = seen.insert(normalize(name), def.name) {
+					panic!("`{}` names both {} and {}", name, other, def.name);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn maps_terminal_keys() {
+		assert_eq!(from_term(Key::Char('q')).unwrap().label, "Q");
+		assert_eq!(from_term(Key::Char('Q')).unwrap().label, "Q");
+		assert_eq!(from_term(Key::Char('!')).unwrap().name, "1");
+		assert_eq!(from_term(Key::Char(' ')).unwrap().label, "Space");
+		assert_eq!(from_term(Key::Backspace).unwrap().label, "Bs");
+		assert_eq!(
+			from_char('a').map(|(def, shift)| (def.name, shift)),
+			Some(("A", false))
+		);
+		assert_eq!(
+			from_char('>').map(|(def, shift)| (def.name, shift)),
+			Some(("Dot", true))
+		);
+		assert_eq!(
+			from_char('\n').map(|(def, shift)| (def.name, shift)),
+			Some(("Enter", false))
+		);
+		assert!(from_char('é').is_none());
+	}
+
+	#[test]
+	fn maps_keycap_labels() {
+		assert_eq!(from_label("Shift", 0).unwrap().name, "LeftShift");
+		assert_eq!(from_label("Shift", 1).unwrap().name, "RightShift");
+		assert_eq!(from_label("Shift", 5).unwrap().name, "LeftShift");
+		assert_eq!(from_label("Caps", 0).unwrap().name, "CapsLock");
+		assert_eq!(from_label("Escape", 0).unwrap().name, "Escape");
+		assert_eq!(lookup("Left").unwrap().term_key(), Some(Key::Left));
+		assert_eq!(lookup("A").unwrap().term_key(), Some(Key::Char('a')));
+	}
+}
@@ -0,0 +1,280 @@
+use evdev::KeyCode;
+use tuirealm::event::Key;
+
+use super::KeyDef;
+
+/// Every key geekCaps knows by name, in rough physical order so listings
+/// read like a keyboard.
+pub static KEYS: &[KeyDef] = &[
+	KeyDef::new("Escape", KeyCode::KEY_ESC, "Esc", &["Esc"]).term(Key::Esc),
+	KeyDef::new("F1", KeyCode::KEY_F1, "F1", &[]).term(Key::Function(1)),
+	KeyDef::new("F2", KeyCode::KEY_F2, "F2", &[]).term(Key::Function(2)),
+	KeyDef::new("F3", KeyCode::KEY_F3, "F3", &[]).term(Key::Function(3)),
+	KeyDef::new("F4", KeyCode::KEY_F4, "F4", &[]).term(Key::Function(4)),
+	KeyDef::new("F5", KeyCode::KEY_F5, "F5", &[]).term(Key::Function(5)),
+	KeyDef::new("F6", KeyCode::KEY_F6, "F6", &[]).term(Key::Function(6)),
+	KeyDef::new("F7", KeyCode::KEY_F7, "F7", &[]).term(Key::Function(7)),
+	KeyDef::new("F8", KeyCode::KEY_F8, "F8", &[]).term(Key::Function(8)),
+	KeyDef::new("F9", KeyCode::KEY_F9, "F9", &[]).term(Key::Function(9)),
+	KeyDef::new("F10", KeyCode::KEY_F10, "F10", &[]).term(Key::Function(10)),
+	KeyDef::new("F11", KeyCode::KEY_F11, "F11", &[]).term(Key::Function(11)),
+	KeyDef::new("F12", KeyCode::KEY_F12, "F12", &[]).term(Key::Function(12)),
+	KeyDef::new("F13", KeyCode::KEY_F13, "F13", &[]).term(Key::Function(13)),
+	KeyDef::new("F14", KeyCode::KEY_F14, "F14", &[]).term(Key::Function(14)),
+	KeyDef::new("F15", KeyCode::KEY_F15, "F15", &[]).term(Key::Function(15)),
+	KeyDef::new("F16", KeyCode::KEY_F16, "F16", &[]).term(Key::Function(16)),
+	KeyDef::new("F17", KeyCode::KEY_F17, "F17", &[]).term(Key::Function(17)),
+	KeyDef::new("F18", KeyCode::KEY_F18, "F18", &[]).term(Key::Function(18)),
+	KeyDef::new("F19", KeyCode::KEY_F19, "F19", &[]).term(Key::Function(19)),
+	KeyDef::new("F20", KeyCode::KEY_F20, "F20", &[]).term(Key::Function(20)),
+	KeyDef::new("F21", KeyCode::KEY_F21, "F21", &[]).term(Key::Function(21)),
+	KeyDef::new("F22", KeyCode::KEY_F22, "F22", &[]).term(Key::Function(22)),
+	KeyDef::new("F23", KeyCode::KEY_F23, "F23", &[]).term(Key::Function(23)),
+	KeyDef::new("F24", KeyCode::KEY_F24, "F24", &[]).term(Key::Function(24)),
+	KeyDef::new(
+		"Grave",
+		KeyCode::KEY_GRAVE,
+		"`",
+		&["`", "Backtick", "Tilde"],
+	)
+	.chars('`', '~'),
+	KeyDef::new("1", KeyCode::KEY_1, "1", &[]).chars('1', '!'),
+	KeyDef::new("2", KeyCode::KEY_2, "2", &[]).chars('2', '@'),
+	KeyDef::new("3", KeyCode::KEY_3, "3", &[]).chars('3', '#'),
+	KeyDef::new("4", KeyCode::KEY_4, "4", &[]).chars('4', '$'),
+	KeyDef::new("5", KeyCode::KEY_5, "5", &[]).chars('5', '%'),
+	KeyDef::new("6", KeyCode::KEY_6, "6", &[]).chars('6', '^'),
+	KeyDef::new("7", KeyCode::KEY_7, "7", &[]).chars('7', '&'),
+	KeyDef::new("8", KeyCode::KEY_8, "8", &[]).chars('8', '*'),
+	KeyDef::new("9", KeyCode::KEY_9, "9", &[]).chars('9', '('),
+	KeyDef::new("0", KeyCode::KEY_0, "0", &[]).chars('0', ')'),
+	KeyDef::new("Minus", KeyCode::KEY_MINUS, "-", &["-", "Dash", "Hyphen"]).chars('-', '_'),
+	KeyDef::new("Equal", KeyCode::KEY_EQUAL, "=", &["=", "Equals"]).chars('=', '+'),
+	KeyDef::new("Backspace", KeyCode::KEY_BACKSPACE, "Bs", &["Bs", "Bksp"]).term(Key::Backspace),
+	KeyDef::new("Tab", KeyCode::KEY_TAB, "Tab", &[]).term(Key::Tab),
+	KeyDef::new("Q", KeyCode::KEY_Q, "Q", &[]).chars('q', 'Q'),
+	KeyDef::new("W", KeyCode::KEY_W, "W", &[]).chars('w', 'W'),
+	KeyDef::new("E", KeyCode::KEY_E, "E", &[]).chars('e', 'E'),
+	KeyDef::new("R", KeyCode::KEY_R, "R", &[]).chars('r', 'R'),
+	KeyDef::new("T", KeyCode::KEY_T, "T", &[]).chars('t', 'T'),
+	KeyDef::new("Y", KeyCode::KEY_Y, "Y", &[]).chars('y', 'Y'),
+	KeyDef::new("U", KeyCode::KEY_U, "U", &[]).chars('u', 'U'),
+	KeyDef::new("I", KeyCode::KEY_I, "I", &[]).chars('i', 'I'),
+	KeyDef::new("O", KeyCode::KEY_O, "O", &[]).chars('o', 'O'),
+	KeyDef::new("P", KeyCode::KEY_P, "P", &[]).chars('p', 'P'),
+	KeyDef::new(
+		"LeftBrace",
+		KeyCode::KEY_LEFTBRACE,
+		"[",
+		&["[", "LeftBracket", "LBracket"],
+	)
+	.chars('[', '{'),
+	KeyDef::new(
+		"RightBrace",
+		KeyCode::KEY_RIGHTBRACE,
+		"]",
+		&["]", "RightBracket", "RBracket"],
+	)
+	.chars(']', '}'),
+	KeyDef::new("Backslash", KeyCode::KEY_BACKSLASH, "\\", &["\\", "Pipe"]).chars('\\', '|'),
+	KeyDef::new("CapsLock", KeyCode::KEY_CAPSLOCK, "Caps", &["Caps"]).term(Key::CapsLock),
+	KeyDef::new("A", KeyCode::KEY_A, "A", &[]).chars('a', 'A'),
+	KeyDef::new("S", KeyCode::KEY_S, "S", &[]).chars('s', 'S'),
+	KeyDef::new("D", KeyCode::KEY_D, "D", &[]).chars('d', 'D'),
+	KeyDef::new("F", KeyCode::KEY_F, "F", &[]).chars('f', 'F'),
+	KeyDef::new("G", KeyCode::KEY_G, "G", &[]).chars('g', 'G'),
+	KeyDef::new("H", KeyCode::KEY_H, "H", &[]).chars('h', 'H'),
+	KeyDef::new("J", KeyCode::KEY_J, "J", &[]).chars('j', 'J'),
+	KeyDef::new("K", KeyCode::KEY_K, "K", &[]).chars('k', 'K'),
+	KeyDef::new("L", KeyCode::KEY_L, "L", &[]).chars('l', 'L'),
+	KeyDef::new("Semicolon", KeyCode::KEY_SEMICOLON, ";", &[";"]).chars(';', ':'),
+	KeyDef::new("Apostrophe", KeyCode::KEY_APOSTROPHE, "'", &["'", "Quote"]).chars('\'', '"'),
+	KeyDef::new("Enter", KeyCode::KEY_ENTER, "Enter", &["Return", "Ret"]).term(Key::Enter),
+	KeyDef::new(
+		"LeftShift",
+		KeyCode::KEY_LEFTSHIFT,
+		"Shift",
+		&["LShift", "Shift"],
+	)
+	.term(Key::ShiftLeft),
+	KeyDef::new(
+		"IntlBackslash",
+		KeyCode::KEY_102ND,
+		"<>",
+		&["NonUsBackslash", "Iso102nd", "102nd"],
+	),
+	KeyDef::new("Z", KeyCode::KEY_Z, "Z", &[]).chars('z', 'Z'),
+	KeyDef::new("X", KeyCode::KEY_X, "X", &[]).chars('x', 'X'),
+	KeyDef::new("C", KeyCode::KEY_C, "C", &[]).chars('c', 'C'),
+	KeyDef::new("V", KeyCode::KEY_V, "V", &[]).chars('v', 'V'),
+	KeyDef::new("B", KeyCode::KEY_B, "B", &[]).chars('b', 'B'),
+	KeyDef::new("N", KeyCode::KEY_N, "N", &[]).chars('n', 'N'),
+	KeyDef::new("M", KeyCode::KEY_M, "M", &[]).chars('m', 'M'),
+	KeyDef::new("Comma", KeyCode::KEY_COMMA, ",", &[","]).chars(',', '<'),
+	KeyDef::new("Dot", KeyCode::KEY_DOT, ".", &[".", "Period"]).chars('.', '>'),
+	KeyDef::new("Slash", KeyCode::KEY_SLASH, "/", &["/"]).chars('/', '?'),
+	KeyDef::new("RightShift", KeyCode::KEY_RIGHTSHIFT, "Shift", &["RShift"]).term(Key::ShiftRight),
+	KeyDef::new(
+		"LeftCtrl",
+		KeyCode::KEY_LEFTCTRL,
+		"Ctrl",
+		&["LCtrl", "Ctrl", "LeftControl", "Control"],
+	)
+	.term(Key::CtrlLeft),
+	KeyDef::new(
+		"RightCtrl",
+		KeyCode::KEY_RIGHTCTRL,
+		"Ctrl",
+		&["RCtrl", "RightControl"],
+	)
+	.term(Key::CtrlRight),
+	KeyDef::new(
+		"LeftAlt",
+		KeyCode::KEY_LEFTALT,
+		"Alt",
+		&["LAlt", "Alt", "Option", "LeftOption"],
+	)
+	.term(Key::AltLeft),
+	KeyDef::new(
+		"RightAlt",
+		KeyCode::KEY_RIGHTALT,
+		"Alt",
+		&["RAlt", "AltGr", "RightOption"],
+	)
+	.term(Key::AltRight),
+	KeyDef::new(
+		"LeftMeta",
+		KeyCode::KEY_LEFTMETA,
+		"Cmd",
+		&[
+			"LMeta",
+			"Meta",
+			"Super",
+			"LeftSuper",
+			"Win",
+			"Windows",
+			"Cmd",
+			"Command",
+			"LeftCmd",
+		],
+	),
+	KeyDef::new(
+		"RightMeta",
+		KeyCode::KEY_RIGHTMETA,
+		"Cmd",
+		&["RMeta", "RightSuper", "RightWin", "RightCmd", "RCmd"],
+	),
+	KeyDef::new(
+		"Menu",
+		KeyCode::KEY_COMPOSE,
+		"Menu",
+		&["Compose", "Application", "ContextMenu"],
+	)
+	.term(Key::Menu),
+	KeyDef::new("Fn", KeyCode::KEY_FN, "Fn", &[]),
+	KeyDef::new("Space", KeyCode::KEY_SPACE, "Space", &["Spacebar"]).chars(' ', ' '),
+	KeyDef::new("Insert", KeyCode::KEY_INSERT, "Ins", &["Ins"]).term(Key::Insert),
+	KeyDef::new("Delete", KeyCode::KEY_DELETE, "Del", &["Del"]).term(Key::Delete),
+	KeyDef::new("Home", KeyCode::KEY_HOME, "Home", &[]).term(Key::Home),
+	KeyDef::new("End", KeyCode::KEY_END, "End", &[]).term(Key::End),
+	KeyDef::new("PageUp", KeyCode::KEY_PAGEUP, "PgUp", &["PgUp"]).term(Key::PageUp),
+	KeyDef::new(
+		"PageDown",
+		KeyCode::KEY_PAGEDOWN,
+		"PgDn",
+		&["PgDn", "PgDown"],
+	)
+	.term(Key::PageDown),
+	KeyDef::new("Up", KeyCode::KEY_UP, "↑", &["UpArrow", "ArrowUp", "↑"]).term(Key::Up),
+	KeyDef::new(
+		"Down",
+		KeyCode::KEY_DOWN,
+		"↓",
+		&["DownArrow", "ArrowDown", "↓"],
+	)
+	.term(Key::Down),
+	KeyDef::new(
+		"Left",
+		KeyCode::KEY_LEFT,
+		"←",
+		&["LeftArrow", "ArrowLeft", "←"],
+	)
+	.term(Key::Left),
+	KeyDef::new(
+		"Right",
+		KeyCode::KEY_RIGHT,
+		"→",
+		&["RightArrow", "ArrowRight", "→"],
+	)
+	.term(Key::Right),
+	KeyDef::new(
+		"PrintScreen",
+		KeyCode::KEY_SYSRQ,
+		"PrtSc",
+		&["Print", "PrtSc", "SysRq"],
+	)
+	.term(Key::PrintScreen),
+	KeyDef::new("ScrollLock", KeyCode::KEY_SCROLLLOCK, "ScrLk", &["ScrLk"]).term(Key::ScrollLock),
+	KeyDef::new("Pause", KeyCode::KEY_PAUSE, "Pause", &["Break"]).term(Key::Pause),
+	KeyDef::new("NumLock", KeyCode::KEY_NUMLOCK, "Num", &[]).term(Key::NumLock),
+	KeyDef::new("Kp0", KeyCode::KEY_KP0, "0", &["Numpad0"]),
+	KeyDef::new("Kp1", KeyCode::KEY_KP1, "1", &["Numpad1"]),
+	KeyDef::new("Kp2", KeyCode::KEY_KP2, "2", &["Numpad2"]),
+	KeyDef::new("Kp3", KeyCode::KEY_KP3, "3", &["Numpad3"]),
+	KeyDef::new("Kp4", KeyCode::KEY_KP4, "4", &["Numpad4"]),
+	KeyDef::new("Kp5", KeyCode::KEY_KP5, "5", &["Numpad5"]),
+	KeyDef::new("Kp6", KeyCode::KEY_KP6, "6", &["Numpad6"]),
+	KeyDef::new("Kp7", KeyCode::KEY_KP7, "7", &["Numpad7"]),
+	KeyDef::new("Kp8", KeyCode::KEY_KP8, "8", &["Numpad8"]),
+	KeyDef::new("Kp9", KeyCode::KEY_KP9, "9", &["Numpad9"]),
+	KeyDef::new(
+		"KpDot",
+		KeyCode::KEY_KPDOT,
+		".",
+		&["NumpadDot", "KpDecimal"],
+	),
+	KeyDef::new("KpEnter", KeyCode::KEY_KPENTER, "Enter", &["NumpadEnter"]),
+	KeyDef::new("KpPlus", KeyCode::KEY_KPPLUS, "+", &["NumpadPlus", "KpAdd"]),
+	KeyDef::new(
+		"KpMinus",
+		KeyCode::KEY_KPMINUS,
+		"-",
+		&["NumpadMinus", "KpSubtract"],
+	),
+	KeyDef::new(
+		"KpAsterisk",
+		KeyCode::KEY_KPASTERISK,
+		"*",
+		&["NumpadAsterisk", "KpMultiply"],
+	),
+	KeyDef::new(
+		"KpSlash",
+		KeyCode::KEY_KPSLASH,
+		"/",
+		&["NumpadSlash", "KpDivide"],
+	),
+	KeyDef::new("KpEqual", KeyCode::KEY_KPEQUAL, "=", &["NumpadEqual"]),
+	KeyDef::new("Mute", KeyCode::KEY_MUTE, "Mute", &["VolumeMute"]),
+	KeyDef::new("VolumeDown", KeyCode::KEY_VOLUMEDOWN, "Vol-", &["VolDown"]),
+	KeyDef::new("VolumeUp", KeyCode::KEY_VOLUMEUP, "Vol+", &["VolUp"]),
+	KeyDef::new(
+		"PlayPause",
+		KeyCode::KEY_PLAYPAUSE,
+		"Play",
+		&["MediaPlayPause"],
+	),
+	KeyDef::new(
+		"NextSong",
+		KeyCode::KEY_NEXTSONG,
+		"Next",
+		&["MediaNext", "NextTrack"],
+	),
+	KeyDef::new(
+		"PreviousSong",
+		KeyCode::KEY_PREVIOUSSONG,
+		"Prev",
+		&["MediaPrevious", "PreviousTrack", "PrevTrack"],
+	),
+	KeyDef::new("BrightnessDown", KeyCode::KEY_BRIGHTNESSDOWN, "Bri-", &[]),
+	KeyDef::new("BrightnessUp", KeyCode::KEY_BRIGHTNESSUP, "Bri+", &[]),
+];
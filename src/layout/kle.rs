@@ -0,0 +1,286 @@
+//! Import of keyboard-layout-editor.com (KLE) layouts.
+//!
+//! KLE describes a keyboard as rows of legends; an object before a legend
+//! sizes the next key (`w`, `h`, and `x2`/`y2`/`w2`/`h2` for L-shapes),
+//! moves it (`x`, and `y` which also moves the rows below) or sets state
+//! such as alignment and colours that we ignore. Legends hold up to
+//! twelve `\n`-separated labels; whichever of them names a registry key
+//! decides the key code.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use evdev::KeyCode;
+use serde_json::Value;
+
+use super::{Area, Cap, Layout};
+use crate::keys;
+
+/// Parses KLE JSON as downloaded from the site, or the site's "raw data"
+/// text (unquoted property names, no outer brackets).
+pub fn parse(source: &str) -> Result<Layout> {
+	let value = parse_json(source)?;
+	let Value::Array(items) = value else {
+		bail!("a KLE layout is a JSON array of rows");
+	};
+
+	let mut name = String::new();
+	let mut rows = Vec::new();
+	let mut used = HashSet::new();
+	let mut seen: HashMap<String, usize> = HashMap::new();
+	// Top of the current row in key units, ahead of the row count once
+	// `y` has moved rows down
+	let mut top = 0.0;
+	for (row_idx, item) in items.iter().enumerate() {
+		match item {
+			Value::Object(meta) if row_idx == 0 => {
+				if let Some(Value::String(meta_name)) = meta.get("name") {
+					name = meta_name.clone();
+				}
+			}
+			Value::Array(row) => {
+				let row_idx = rows.len();
+				let caps = parse_row(row, row_idx, &mut top, &mut used, &mut seen)
+					.with_context(|| format!("row {}", row_idx + 1))?;
+				rows.push(caps);
+				top += 1.0;
+			}
+			_ => bail!("item {} is neither a row nor layout metadata", row_idx + 1),
+		}
+	}
+	if rows.is_empty() {
+		bail!("the KLE layout has no rows");
+	}
+
+	Ok(Layout { name, rows })
+}
+
+fn parse_row(
+	row: &[Value],
+	row_idx: usize,
+	top: &mut f32,
+	used: &mut HashSet<KeyCode>,
+	seen: &mut HashMap<String, usize>,
+) -> Result<Vec<Cap>> {
+	let mut caps = Vec::new();
+	let mut gap = 0.0;
+	let mut width = 1.0;
+	let mut height = 1.0;
+	let mut second = None;
+	for (col_idx, item) in row.iter().enumerate() {
+		match item {
+			Value::Object(props) => {
+				let number = |key: &str| props.get(key).and_then(Value::as_f64).map(|n| n as f32);
+				// Negative offsets overlap keys, which a terminal grid cannot.
+				gap += number("x").unwrap_or(0.0).max(0.0);
+				*top += number("y").unwrap_or(0.0);
+				width = number("w").unwrap_or(width);
+				height = number("h").unwrap_or(height);
+				let part = Area {
+					x: number("x2").unwrap_or(0.0),
+					y: number("y2").unwrap_or(0.0),
+					width: number("w2").unwrap_or(width),
+					height: number("h2").unwrap_or(height),
+				};
+				let body = Area {
+					x: 0.0,
+					y: 0.0,
+					width,
+					height,
+				};
+				second = (part != body).then_some(part);
+			}
+			Value::String(legend) => {
+				let legends: Vec<String> = legend
+					.split('\n')
+					.map(clean_legend)
+					.filter(|legend| !legend.is_empty())
+					.collect();
+				let code = resolve(&legends, seen).filter(|code| used.insert(*code));
+				let label = match code {
+					Some(code) => keys::label_of(code),
+					None => legends.join(" "),
+				};
+				caps.push(Cap {
+					code,
+					label,
+					width,
+					gap,
+					height,
+					offset: *top - row_idx as f32,
+					second: second.take(),
+				});
+				gap = 0.0;
+				width = 1.0;
+				height = 1.0;
+			}
+			_ => bail!(
+				"column {}: expected a legend or key properties",
+				col_idx + 1
+			),
+		}
+	}
+	Ok(caps)
+}
+
+/// The key the first recognisable legend names. Characters resolve as on a
+/// US layout (`!` is the 1 key); repeated labels such as `Shift` go left
+/// then right.
+fn resolve(legends: &[String], seen: &mut HashMap<String, usize>) -> Option<KeyCode> {
+	for legend in legends {
+		let mut chars = legend.chars();
+		if let (Some(ch), None) = (chars.next(), chars.next()) {
+			if let Some((def, _)) = keys::from_char(ch.to_ascii_lowercase()) {
+				return Some(def.code);
+			}
+		}
+
+		let nth = seen.entry(legend.to_lowercase()).or_default();
+		if let Some(def) = keys::from_label(legend, *nth) {
+			*nth += 1;
+			return Some(def.code);
+		}
+	}
+	None
+}
+
+/// Strips the HTML KLE allows in legends and decodes common entities.
+fn clean_legend(legend: &str) -> String {
+	let mut text = String::new();
+	let mut in_tag = false;
+	for ch in legend.chars() {
+		match ch {
+			'<' => in_tag = true,
+			'>' if in_tag => in_tag = false,
+			ch if !in_tag => text.push(ch),
+			_ => {}
+		}
+	}
+	text
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&amp;", "&")
+		.trim()
+		.to_string()
+}
+
+fn parse_json(source: &str) -> Result<Value> {
+	if let Ok(value) = serde_json::from_str(source) {
+		return Ok(value);
+	}
+	let quoted = quote_property_names(source);
+	serde_json::from_str(&quoted)
+		.or_else(|_| serde_json::from_str(&format!("[{}]", quoted)))
+		.context("Not valid KLE JSON or raw data")
+}
+
+/// Turns `{w:1.5}` into `{"w":1.5}`, leaving strings alone.
+fn quote_property_names(source: &str) -> String {
+	let mut out = String::with_capacity(source.len());
+	let mut chars = source.chars().peekable();
+	while let Some(ch) = chars.next() {
+		if ch == '"' {
+			out.push(ch);
+			while let Some(ch) = chars.next() {
+				out.push(ch);
+				match ch {
+					'\\' => out.extend(chars.next()),
+					'"' => break,
+					_ => {}
+				}
+			}
+		} else if ch.is_ascii_alphabetic() || ch == '_' {
+			let mut word = ch.to_string();
+			while let Some(ch) = chars.next_if(|ch| ch.is_ascii_alphanumeric() || *ch == '_') {
+				word.push(ch);
+			}
+			let is_key = chars.clone().find(|ch| !ch.is_whitespace()) == Some(':');
+			if is_key {
+				out.push('"');
+				out.push_str(&word);
+				out.push('"');
+			} else {
+				out.push_str(&word);
+			}
+		} else {
+			out.push(ch);
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn imports_downloaded_json() {
+		let layout = parse(
+			r#"[
+				{ "name": "Tiny" },
+				["~\n`", "!\n1", { "w": 2 }, "Backspace"],
+				[{ "w": 1.5 }, "Tab", "Q", { "x": 0.25 }, "<b>W</b>"],
+				[{ "w": 2.25 }, "Shift", "Z", { "w": 2.75 }, "Shift"]
+			]"#,
+		)
+		.unwrap();
+
+		assert_eq!(layout.name, "Tiny");
+		assert_eq!(layout.rows.len(), 3);
+		assert_eq!(layout.rows[0][0].code, Some(KeyCode::KEY_GRAVE));
+		assert_eq!(layout.rows[0][1].code, Some(KeyCode::KEY_1));
+		assert_eq!(layout.rows[0][1].label, "1");
+		assert_eq!(layout.rows[0][2].width, 2.0);
+		assert_eq!(layout.rows[1][0].width, 1.5);
+		assert_eq!(layout.rows[1][1].width, 1.0);
+		assert_eq!(layout.rows[1][2].code, Some(KeyCode::KEY_W));
+		assert_eq!(layout.rows[1][2].gap, 0.25);
+		assert_eq!(layout.rows[2][0].code, Some(KeyCode::KEY_LEFTSHIFT));
+		assert_eq!(layout.rows[2][2].code, Some(KeyCode::KEY_RIGHTSHIFT));
+	}
+
+	#[test]
+	fn imports_raw_data() {
+		let layout = parse("[\"Esc\",{x:1,w:1.25},\"F1\"],\n[{a:7},\"Fancy\"]").unwrap();
+		assert_eq!(layout.rows[0][1].code, Some(KeyCode::KEY_F1));
+		assert_eq!(layout.rows[0][1].gap, 1.0);
+		assert_eq!(layout.rows[0][1].width, 1.25);
+
+		// Unknown legends become blank caps that keep their text.
+		assert_eq!(layout.rows[1][0].code, None);
+		assert_eq!(layout.rows[1][0].label, "Fancy");
+	}
+
+	#[test]
+	fn imports_shapes_and_offsets() {
+		let layout = parse(
+			r#"[
+				["Esc"],
+				[{ "y": 0.5 }, "Q", "]", { "x": 0.25, "w": 1.25, "h": 2, "w2": 1.5, "h2": 1, "x2": -0.25 }, "Enter"],
+				["A", { "h": 2 }, "Num +"]
+			]"#,
+		)
+		.unwrap();
+
+		assert_eq!(layout.rows[1][0].offset, 0.5);
+		let enter = &layout.rows[1][2];
+		assert_eq!(enter.code, Some(KeyCode::KEY_ENTER));
+		assert_eq!((enter.width, enter.height, enter.gap), (1.25, 2.0, 0.25));
+		assert_eq!(
+			enter.second,
+			Some(Area {
+				x: -0.25,
+				y: 0.0,
+				width: 1.5,
+				height: 1.0
+			})
+		);
+
+		// `y` moves every row below too; sizes only apply to the next key.
+		assert_eq!(layout.rows[2][0].offset, 0.5);
+		assert_eq!(layout.rows[2][0].height, 1.0);
+		assert_eq!(layout.rows[2][1].height, 2.0);
+		assert_eq!(layout.rows[2][1].second, None);
+	}
+}
@@ -0,0 +1,618 @@
+pub mod kle;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use evdev::KeyCode;
+use serde::{Deserialize, Serialize};
+
+use crate::config::line_column;
+use crate::keys;
+
+/// Terminal columns drawn for one key unit (the width of a letter key).
+pub const CELLS_PER_UNIT: f32 = 5.0;
+
+/// Terminal lines drawn for one key unit of height.
+pub const LINES_PER_UNIT: f32 = 3.0;
+
+/// Layouts shipped with geekCaps, by the name `--layout` accepts.
+pub const BUNDLED: &[(&str, &str)] = &[
+	("ansi-60", include_str!("bundled/ansi-60.toml")),
+	("tkl", include_str!("bundled/tkl.toml")),
+	("full-size", include_str!("bundled/full-size.toml")),
+	("iso", include_str!("bundled/iso.toml")),
+	("ortho-40", include_str!("bundled/ortho-40.toml")),
+];
+
+/// The physical shape of a keyboard: rows of caps, left to right. Rows are
+/// one unit apart unless a cap sets its own `offset`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawLayout", into = "RawLayout")]
+pub struct Layout {
+	pub name: String,
+	pub rows: Vec<Vec<Cap>>,
+}
+
+/// One key on a [`Layout`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cap {
+	/// The physical key, `None` for blank caps.
+	pub code: Option<KeyCode>,
+	pub label: String,
+	/// Width in key units.
+	pub width: f32,
+	/// Empty space left of the cap, in key units.
+	pub gap: f32,
+	/// Height in key units, e.g. 2 for a numpad Enter.
+	pub height: f32,
+	/// How far the cap sits below its row, in key units. Negative moves it up.
+	pub offset: f32,
+	/// Second rectangle of an L-shaped key such as ISO Enter, relative to
+	/// the cap's top-left corner.
+	pub second: Option<Area>,
+}
+
+impl Cap {
+	/// A 1u cap for a registry key, labelled as in the registry.
+	pub fn key(code: KeyCode) -> Self {
+		Self {
+			code: Some(code),
+			label: keys::label_of(code),
+			width: 1.0,
+			gap: 0.0,
+			height: 1.0,
+			offset: 0.0,
+			second: None,
+		}
+	}
+}
+
+/// A rectangle in key units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Area {
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl Area {
+	/// The terminal cells covered, as `(x, y, width, height)`.
+	pub fn cells(&self) -> (u16, u16, u16, u16) {
+		let (x, width) = cells(self.x, self.width);
+		let (y, height) = lines(self.y, self.height);
+		(x, y, width, height)
+	}
+}
+
+/// A cap at its absolute position on the keyboard.
+#[derive(Debug, Clone, Copy)]
+pub struct Placed<'a> {
+	pub row: usize,
+	pub col: usize,
+	pub cap: &'a Cap,
+	/// Top-left corner in key units from the keyboard's top-left corner.
+	pub x: f32,
+	pub y: f32,
+}
+
+impl Placed<'_> {
+	/// The rectangles the cap covers: its body, then the second part of an
+	/// L-shaped key.
+	pub fn areas(&self) -> Vec<Area> {
+		let body = Area {
+			x: self.x,
+			y: self.y,
+			width: self.cap.width,
+			height: self.cap.height,
+		};
+		let second = self.cap.second.map(|second| Area {
+			x: self.x + second.x,
+			y: self.y + second.y,
+			..second
+		});
+		std::iter::once(body).chain(second).collect()
+	}
+}
+
+/// Converts a span in key units to terminal columns. Rounding the edges
+/// rather than each width keeps long rows from drifting.
+pub fn cells(start: f32, width: f32) -> (u16, u16) {
+	span(start, width, CELLS_PER_UNIT)
+}
+
+/// Converts a span in key units to terminal lines, like [`cells`].
+pub fn lines(start: f32, height: f32) -> (u16, u16) {
+	span(start, height, LINES_PER_UNIT)
+}
+
+fn span(start: f32, length: f32, per_unit: f32) -> (u16, u16) {
+	// Negative positions saturate to the first cell
+	let first = (start * per_unit).round() as u16;
+	let last = ((start + length) * per_unit).round() as u16;
+	(first, last.saturating_sub(first))
+}
+
+impl Layout {
+	/// Reads a layout file, as JSON if the name ends in `.json` and as TOML
+	/// otherwise. Files holding an array rather than a table are imported
+	/// as keyboard-layout-editor.com layouts.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let path = path.as_ref();
+		let source = fs::read_to_string(path)
+			.with_context(|| format!("Cannot read layout file {}", path.display()))?;
+
+		if source.trim_start().starts_with('[') {
+			let mut layout =
+				kle::parse(&source).with_context(|| format!("Invalid KLE layout {}", path.display()))?;
+			if layout.name.is_empty() {
+				layout.name = path
+					.file_stem()
+					.map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+			}
+			return Ok(layout);
+		}
+
+		if path.extension().is_some_and(|ext| ext == "json") {
+			return serde_json::from_str(&source).map_err(|err| {
+				let location = format!("{}:{}:{}", path.display(), err.line(), err.column());
+				anyhow::Error::new(err).context(format!("Invalid layout {}", location))
+			});
+		}
+
+		toml::from_str(&source).map_err(|err| {
+			let location = match err.span() {
+				Some(span) => {
+					let (line, column) = line_column(&source, span.start);
+					format!("{}:{}:{}", path.display(), line, column)
+				}
+				None => path.display().to_string(),
+			};
+			anyhow::Error::new(err).context(format!("Invalid layout {}", location))
+		})
+	}
+
+	/// A bundled layout by name, e.g. `tkl`.
+	pub fn bundled(name: &str) -> Option<Self> {
+		BUNDLED
+			.iter()
+			.find(|(bundled, _)| bundled.eq_ignore_ascii_case(name))
+			.map(|(_, source)| source.parse().expect("bundled layouts are valid"))
+	}
+
+	/// Resolves a `--layout` argument: a bundled layout name or a file.
+	pub fn find(spec: &str) -> Result<Self> {
+		if let Some(layout) = Self::bundled(spec) {
+			return Ok(layout);
+		}
+		let path = Path::new(spec);
+		if !path.exists() {
+			let names: Vec<&str> = BUNDLED.iter().map(|(name, _)| *name).collect();
+			bail!(
+				"no layout file `{}`, bundled layouts are: {}",
+				spec,
+				names.join(", ")
+			);
+		}
+		Self::load(path)
+	}
+
+	/// Builds a layout from rows of `label[:columns]` entries, the format
+	/// `Keyboard` used before layout files. `:columns` alone is a gap and
+	/// repeated labels map to the left then the right key.
+	pub fn from_labels(rows: Vec<Vec<&str>>) -> Self {
+		let mut seen: HashMap<String, usize> = HashMap::new();
+		let rows = rows
+			.into_iter()
+			.map(|row| {
+				let mut caps = Vec::new();
+				let mut gap = 0.0;
+				for entry in row {
+					let (label, columns) = match entry.split_once(':') {
+						Some((label, columns)) => (label, columns.parse::<f32>().unwrap_or(CELLS_PER_UNIT)),
+						None => (entry, CELLS_PER_UNIT),
+					};
+					let width = columns / CELLS_PER_UNIT;
+					if label.is_empty() {
+						gap += width;
+						continue;
+					}
+
+					let nth = seen.entry(label.to_lowercase()).or_default();
+					let code = keys::from_label(label, *nth).map(|def| def.code);
+					*nth += 1;
+					caps.push(Cap {
+						code,
+						label: label.to_string(),
+						width,
+						gap,
+						height: 1.0,
+						offset: 0.0,
+						second: None,
+					});
+					gap = 0.0;
+				}
+				caps
+			})
+			.collect();
+
+		Self {
+			name: String::new(),
+			rows,
+		}
+	}
+
+	/// Every cap at its absolute position, row by row.
+	pub fn placed(&self) -> Vec<Placed<'_>> {
+		let mut placed = Vec::new();
+		for (row_idx, row) in self.rows.iter().enumerate() {
+			let mut x = 0.0;
+			for (col_idx, cap) in row.iter().enumerate() {
+				x += cap.gap;
+				placed.push(Placed {
+					row: row_idx,
+					col: col_idx,
+					cap,
+					x,
+					y: row_idx as f32 + cap.offset,
+				});
+				x += cap.width;
+			}
+		}
+		placed
+	}
+
+	pub fn to_toml(&self) -> Result<String> {
+		toml::to_string_pretty(self).context("Cannot serialize layout")
+	}
+}
+
+impl Default for Layout {
+	/// The bundled ANSI 60% layout.
+	fn default() -> Self {
+		Self::bundled("ansi-60").expect("ansi-60 is bundled")
+	}
+}
+
+impl FromStr for Layout {
+	type Err = toml::de::Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		toml::from_str(s)
+	}
+}
+
+/// The on-disk shape of a layout. A cap is either a key name for a plain
+/// 1u key or a table such as `{ key = "Tab", width = 1.5 }`. L-shaped keys
+/// add a `second` rectangle, e.g. ISO Enter is
+/// `{ key = "Enter", width = 1.25, height = 2, second = { x = -0.25, width = 1.5 } }`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLayout {
+	name: String,
+	rows: Vec<Vec<RawCap>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawCap {
+	Key(String),
+	Table(RawCapTable),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCapTable {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	key: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	label: Option<String>,
+	#[serde(default = "one", skip_serializing_if = "is_one")]
+	width: f32,
+	#[serde(default, skip_serializing_if = "is_zero")]
+	gap: f32,
+	#[serde(default = "one", skip_serializing_if = "is_one")]
+	height: f32,
+	#[serde(default, skip_serializing_if = "is_zero")]
+	offset: f32,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	second: Option<RawArea>,
+}
+
+impl RawCapTable {
+	fn key(key: String) -> Self {
+		Self {
+			key: Some(key),
+			label: None,
+			width: 1.0,
+			gap: 0.0,
+			height: 1.0,
+			offset: 0.0,
+			second: None,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawArea {
+	#[serde(default, skip_serializing_if = "is_zero")]
+	x: f32,
+	#[serde(default, skip_serializing_if = "is_zero")]
+	y: f32,
+	#[serde(default = "one", skip_serializing_if = "is_one")]
+	width: f32,
+	#[serde(default = "one", skip_serializing_if = "is_one")]
+	height: f32,
+}
+
+fn one() -> f32 {
+	1.0
+}
+
+fn is_one(value: &f32) -> bool {
+	*value == 1.0
+}
+
+fn is_zero(value: &f32) -> bool {
+	*value == 0.0
+}
+
+impl TryFrom<RawLayout> for Layout {
+	type Error = String;
+
+	fn try_from(raw: RawLayout) -> Result<Self, Self::Error> {
+		if raw.rows.is_empty() {
+			return Err("a layout needs at least one row".to_string());
+		}
+
+		let mut placed: HashMap<KeyCode, (usize, usize)> = HashMap::new();
+		let mut rows = Vec::new();
+		for (row_idx, row) in raw.rows.into_iter().enumerate() {
+			let mut caps = Vec::new();
+			for (col_idx, cap) in row.into_iter().enumerate() {
+				let at = (row_idx + 1, col_idx + 1);
+				let fail = |message: String| format!("row {}, column {}: {}", at.0, at.1, message);
+
+				let table = match cap {
+					RawCap::Key(key) => RawCapTable::key(key),
+					RawCap::Table(table) => table,
+				};
+
+				if !(table.width.is_finite() && table.width > 0.0) {
+					return Err(fail(format!("width must be positive, not {}", table.width)));
+				}
+				if !(table.height.is_finite() && table.height > 0.0) {
+					return Err(fail(format!(
+						"height must be positive, not {}",
+						table.height
+					)));
+				}
+				if !(table.gap.is_finite() && table.gap >= 0.0) {
+					return Err(fail(format!("gap cannot be negative, not {}", table.gap)));
+				}
+				if !table.offset.is_finite() {
+					return Err(fail(format!(
+						"offset must be a number, not {}",
+						table.offset
+					)));
+				}
+				if let Some(second) = &table.second {
+					let sizes = [second.width, second.height];
+					if !sizes.iter().all(|size| size.is_finite() && *size > 0.0) {
+						return Err(fail(
+							"the second part needs a positive width and height".to_string(),
+						));
+					}
+					if !(second.x.is_finite() && second.y.is_finite()) {
+						return Err(fail("the second part needs a finite x and y".to_string()));
+					}
+				}
+
+				let code = match &table.key {
+					Some(key) => Some(keys::parse_key(key).map_err(|err| fail(err.to_string()))?),
+					None => None,
+				};
+				if let Some(code) = code {
+					if let Some((row, col)) = placed.insert(code, at) {
+						return Err(fail(format!(
+							"`{}` is already at row {}, column {}",
+							keys::name_of(code),
+							row,
+							col
+						)));
+					}
+				}
+
+				let label = match (table.label, code) {
+					(Some(label), _) => label,
+					(None, Some(code)) => keys::label_of(code),
+					(None, None) => String::new(),
+				};
+				caps.push(Cap {
+					code,
+					label,
+					width: table.width,
+					gap: table.gap,
+					height: table.height,
+					offset: table.offset,
+					second: table.second.map(|second| Area {
+						x: second.x,
+						y: second.y,
+						width: second.width,
+						height: second.height,
+					}),
+				});
+			}
+			rows.push(caps);
+		}
+
+		Ok(Self {
+			name: raw.name,
+			rows,
+		})
+	}
+}
+
+impl From<Layout> for RawLayout {
+	fn from(layout: Layout) -> Self {
+		let rows = layout
+			.rows
+			.into_iter()
+			.map(|row| {
+				row
+					.into_iter()
+					.map(|cap| {
+						let key = cap.code.map(keys::name_of);
+						let label = match cap.code {
+							Some(code) if cap.label == keys::label_of(code) => None,
+							_ if cap.label.is_empty() => None,
+							_ => Some(cap.label),
+						};
+						let plain = cap.width == 1.0
+							&& cap.gap == 0.0
+							&& cap.height == 1.0
+							&& cap.offset == 0.0
+							&& cap.second.is_none();
+						match (key, label) {
+							(Some(key), None) if plain => RawCap::Key(key),
+							(key, label) => RawCap::Table(RawCapTable {
+								key,
+								label,
+								width: cap.width,
+								gap: cap.gap,
+								height: cap.height,
+								offset: cap.offset,
+								second: cap.second.map(|second| RawArea {
+									x: second.x,
+									y: second.y,
+									width: second.width,
+									height: second.height,
+								}),
+							}),
+						}
+					})
+					.collect()
+			})
+			.collect();
+
+		RawLayout {
+			name: layout.name,
+			rows,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bundled_layouts_parse() {
+		for (name, _) in BUNDLED {
+			let layout = Layout::bundled(name).unwrap();
+			assert!(!layout.rows.is_empty(), "{}", name);
+		}
+		let ansi = Layout::default();
+		assert_eq!(ansi.rows.len(), 5);
+		assert_eq!(ansi.rows[0].len(), 14);
+		assert_eq!(ansi.rows[3][0].code, Some(KeyCode::KEY_LEFTSHIFT));
+	}
+
+	#[test]
+	fn round_trips() {
+		for name in ["tkl", "iso", "full-size"] {
+			let layout = Layout::bundled(name).unwrap();
+			let parsed: Layout = layout.to_toml().unwrap().parse().unwrap();
+			assert_eq!(parsed, layout, "{}", name);
+		}
+	}
+
+	#[test]
+	fn places_tall_and_l_shaped_keys() {
+		let layout = Layout::bundled("iso").unwrap();
+		let placed = layout.placed();
+		let enter = placed
+			.iter()
+			.find(|placed| placed.cap.code == Some(KeyCode::KEY_ENTER))
+			.unwrap();
+		assert_eq!((enter.row, enter.x, enter.y), (1, 13.75, 1.0));
+		assert_eq!(
+			enter.areas(),
+			vec![
+				Area {
+					x: 13.75,
+					y: 1.0,
+					width: 1.25,
+					height: 2.0
+				},
+				Area {
+					x: 13.5,
+					y: 1.0,
+					width: 1.5,
+					height: 1.0
+				},
+			]
+		);
+		assert_eq!(enter.areas()[0].cells(), (69, 3, 6, 6));
+
+		let layout: Layout = "name = \"x\"\nrows = [[\"Q\", { key = \"W\", offset = 0.5 }]]\n"
+			.parse()
+			.unwrap();
+		assert_eq!(layout.placed()[1].y, 0.5);
+		assert_eq!(layout.placed()[1].areas()[0].cells(), (5, 2, 5, 3));
+	}
+
+	#[test]
+	fn reports_row_and_column() {
+		let err = "name = \"x\"\nrows = [[\"Q\", \"W\"], [\"A\", { key = \"Sx\", width = 1.5 }]]\n"
+			.parse::<Layout>()
+			.unwrap_err();
+		assert!(err
+			.message()
+			.starts_with("row 2, column 2: unknown key `Sx`"));
+
+		let err = "name = \"x\"\nrows = [[\"Q\", { key = \"W\", width = 0 }]]\n"
+			.parse::<Layout>()
+			.unwrap_err();
+		assert!(err
+			.message()
+			.starts_with("row 1, column 2: width must be positive"));
+
+		let err = "name = \"x\"\nrows = [[\"Q\"], [\"q\"]]\n"
+			.parse::<Layout>()
+			.unwrap_err();
+		assert_eq!(
+			err.message(),
+			"row 2, column 1: `Q` is already at row 1, column 1"
+		);
+	}
+
+	#[test]
+	fn loads_json() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("layout.json");
+		fs::write(
+			&path,
+			r#"{ "name": "macro pad", "rows": [["F13", { "key": "F14", "width": 2, "gap": 0.5 }]] }"#,
+		)
+		.unwrap();
+		let layout = Layout::load(&path).unwrap();
+
+		assert_eq!(layout.rows[0][1].width, 2.0);
+		assert_eq!(layout.rows[0][1].gap, 0.5);
+		assert_eq!(layout.rows[0][1].label, "F14");
+	}
+
+	#[test]
+	fn converts_units_to_cells() {
+		assert_eq!(cells(0.0, 1.5), (0, 8));
+		assert_eq!(cells(1.5, 1.0), (8, 5));
+		assert_eq!(cells(2.5, 6.25), (13, 31));
+		assert_eq!(lines(0.5, 2.0), (2, 6));
+		assert_eq!(cells(-0.25, 1.5), (0, 6));
+	}
+}
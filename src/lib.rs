@@ -0,0 +1,63 @@
+extern crate tuirealm;
+
+use evdev::KeyCode;
+
+pub mod app;
+pub mod cli;
+pub mod components;
+pub mod config;
+pub mod device;
+pub mod keys;
+pub mod layout;
+pub mod recording;
+pub mod remap;
+pub mod stats;
+
+#[derive(Debug, PartialEq)]
+pub enum Msg {
+	AppClose,
+	/// A physical key went down, by registry name.
+	KeyPressed(String),
+	/// Something changed on screen without user input, e.g. a fade.
+	Redraw,
+	/// Enter on a keycap: choose what this key should send.
+	EditKey(KeyCode),
+	/// Map the first key to the second in the edited config.
+	Remap(KeyCode, KeyCode),
+	/// Drop the mapping of a key from the edited config.
+	Unmap(KeyCode),
+	EditCancelled,
+	/// Show what saving the edited config would change.
+	SaveRequested,
+	SaveConfirmed,
+	SaveCancelled,
+	/// Show this layer of the config on the keyboard.
+	ShowLayer(usize),
+	NextLayer,
+	PreviousLayer,
+	/// Colour the keycaps by how often they are pressed, or stop.
+	ToggleHeatmap,
+	/// Forget the press counts the heatmap is drawn from.
+	ResetStats,
+}
+
+/// Events fed into the application besides terminal input.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+pub enum UserEvent {
+	/// A physical key went down on the watched device.
+	KeyDown(KeyCode),
+	/// A physical key came back up.
+	KeyUp(KeyCode),
+	/// The layer the running daemon has active, `None` once it stops.
+	DaemonLayer(Option<String>),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum Id {
+	Keycap,
+	Keyboard,
+	KeyPicker,
+	SavePreview,
+	StatusBar,
+	LayerPanel,
+}
@@ -1,55 +1,80 @@
-extern crate tuirealm;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use tuirealm::application::PollStrategy;
-use tuirealm::Update;
+use anyhow::Result;
+use clap::Parser;
+use tuirealm::listener::Poll;
 
-mod app;
-mod components;
-use app::model::Model;
+use geekcaps::app::input::EvdevPort;
+use geekcaps::app::model::Model;
+use geekcaps::cli::{self, Cli, Command};
+use geekcaps::config::edit::ConfigEditor;
+use geekcaps::device::{self, EvdevProbe};
+use geekcaps::layout::Layout;
+use geekcaps::stats::Stats;
+use geekcaps::UserEvent;
 
-#[derive(Debug, PartialEq)]
-pub enum Msg {
-	AppClose,
-	CounterChanged(isize),
-	KeyPressed(String),
-}
+fn main() -> Result<()> {
+	let cli = Cli::parse();
 
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
-pub enum Id {
-	Keycap,
-	SimpleCounter,
-	Keyboard,
+	match cli.command {
+		Some(Command::ListDevices(args)) => cli::list_devices::run(args),
+		Some(Command::Init(args)) => cli::init::run(args),
+		Some(Command::Start(args)) => cli::start::run(args),
+		Some(Command::Stats(args)) => cli::stats::run(args),
+		Some(Command::Monitor(args)) => cli::monitor::run(args),
+		Some(Command::Validate(args)) => match cli::validate::run(args)? {
+			0 => Ok(()),
+			status => process::exit(status),
+		},
+		Some(Command::Record(args)) => cli::record::run(args),
+		Some(Command::Replay(args)) => cli::replay::run(args),
+		None => {
+			let layout = Layout::find(&cli.layout)?;
+			let editor = cli.config.as_deref().map(ConfigEditor::open).transpose()?;
+			let stats = stats_path(cli.stats, editor.as_ref());
+			run_tui(open_input(cli.device.as_deref())?, &layout, editor, stats);
+			Ok(())
+		}
+	}
 }
 
-fn main() {
-	let mut model = Model::default();
-	let _ = model.terminal.enter_alternate_screen();
-	let _ = model.terminal.enable_raw_mode();
-
-	while !model.quit {
-		match model.app.tick(PollStrategy::Once) {
-			Err(err) => {
-				println!("Application error: {}", err);
-			}
-			Ok(messages) if messages.len() > 0 => {
-				model.redraw = true;
-				for msg in messages.into_iter() {
-					let mut msg = Some(msg);
-					while msg.is_some() {
-						msg = model.update(msg);
-					}
-				}
-			}
-			_ => {}
-		}
+/// Where the view keeps its press counts: the file given, or the one the
+/// config counts into. `None` keeps them to the session, as the view sees
+/// every key typed anywhere.
+fn stats_path(file: Option<PathBuf>, editor: Option<&ConfigEditor>) -> Option<PathBuf> {
+	file.or_else(|| {
+		let stats = editor?.config().ok()?.stats?;
+		stats.file.or_else(Stats::default_path)
+	})
+}
 
-		if model.redraw {
-			model.view();
-			model.redraw = false;
+/// Opens the device the keyboard view follows. Without an explicit device
+/// the view falls back to terminal key events if no keyboard can be read.
+fn open_input(path: Option<&Path>) -> Result<Option<EvdevPort>> {
+	if let Some(path) = path {
+		return EvdevPort::open(path).map(Some);
+	}
+	match device::select(&EvdevProbe, None).and_then(|info| EvdevPort::open(&info.path)) {
+		Ok(port) => Ok(Some(port)),
+		Err(err) => {
+			eprintln!("{:#}, showing terminal key events only", err);
+			Ok(None)
 		}
 	}
+}
 
-	let _ = model.terminal.leave_alternate_screen();
-	let _ = model.terminal.disable_raw_mode();
-	let _ = model.terminal.clear_screen();
+fn run_tui(
+	input: Option<EvdevPort>,
+	layout: &Layout,
+	editor: Option<ConfigEditor>,
+	stats: Option<PathBuf>,
+) {
+	let input = input.map(|port| Box::new(port) as Box<dyn Poll<UserEvent>>);
+	let mut model = Model::new(input, layout, editor, stats);
+	model.run();
+
+	if let Err(err) = model.save_stats() {
+		eprintln!("{:#}", err);
+	}
 }
@@ -0,0 +1,305 @@
+//! Raw evdev event streams captured by `record` and played back by
+//! `replay`, one JSON object per line:
+//! `{"time_us":1712000000123456,"type":1,"code":58,"value":1}`.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use evdev::{EventType, InputEvent, KeyCode};
+use serde::{Deserialize, Serialize};
+
+use crate::remap::{Engine, KeyEvent, KeyState, Keymap, Schedule};
+
+/// An input event as the kernel reported it, synchronisation and
+/// scancode events included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawEvent {
+	/// Microseconds since the epoch, on the evdev clock.
+	pub time_us: u64,
+	#[serde(rename = "type")]
+	pub kind: u16,
+	pub code: u16,
+	pub value: i32,
+}
+
+impl RawEvent {
+	pub fn from_input(event: &InputEvent) -> Self {
+		let time = event
+			.timestamp()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap_or_default();
+		Self {
+			time_us: time.as_micros() as u64,
+			kind: event.event_type().0,
+			code: event.code(),
+			value: event.value(),
+		}
+	}
+
+	pub fn time(&self) -> Duration {
+		Duration::from_micros(self.time_us)
+	}
+
+	/// The key event this is, `None` for other event types.
+	pub fn key_event(&self) -> Option<KeyEvent> {
+		if self.kind != EventType::KEY.0 {
+			return None;
+		}
+		KeyState::from_value(self.value)
+			.map(|state| KeyEvent::new(KeyCode::new(self.code), state, self.time()))
+	}
+}
+
+/// Appends events to a recording.
+pub fn write<W: Write>(out: &mut W, events: &[RawEvent]) -> Result<()> {
+	for event in events {
+		serde_json::to_writer(&mut *out, event).context("Cannot serialize event")?;
+		writeln!(out)?;
+	}
+	Ok(())
+}
+
+/// Reads a recording. Blank lines are skipped; anything else that is not
+/// an event is an error naming its line.
+pub fn read<R: BufRead>(input: R) -> Result<Vec<RawEvent>> {
+	let mut events = Vec::new();
+	for (idx, line) in input.lines().enumerate() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+		let event =
+			serde_json::from_str(&line).with_context(|| format!("Invalid event on line {}", idx + 1))?;
+		events.push(event);
+	}
+	Ok(events)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<RawEvent>> {
+	let path = path.as_ref();
+	let file =
+		File::open(path).with_context(|| format!("Cannot read recording {}", path.display()))?;
+	let events =
+		read(BufReader::new(file)).with_context(|| format!("Invalid recording {}", path.display()))?;
+	if events.is_empty() {
+		bail!("{} holds no events", path.display());
+	}
+	Ok(events)
+}
+
+/// What the engine makes of `events` with `keymap`, in the order the
+/// daemon would emit it: decisions are settled at their deadlines rather
+/// than when the next key happens to arrive, and macro steps keep their
+/// delays without holding up what the engine emits meanwhile.
+pub fn remap(keymap: Keymap, events: &[KeyEvent]) -> Vec<KeyEvent> {
+	let mut engine = Engine::new(keymap);
+	let mut schedule = Schedule::default();
+	let mut out = Vec::new();
+	let mut emit = |emitted: Vec<KeyEvent>, now: Duration, out: &mut Vec<KeyEvent>| {
+		out.extend(schedule.due(now));
+		for event in emitted {
+			if event.time <= now {
+				out.push(event);
+			} else {
+				schedule.push(event);
+			}
+		}
+	};
+	for event in events {
+		while let Some(deadline) = engine
+			.next_deadline()
+			.filter(|deadline| *deadline <= event.time)
+		{
+			emit(engine.tick(deadline), deadline, &mut out);
+		}
+		emit(engine.process(*event), event.time, &mut out);
+	}
+	while let Some(deadline) = engine.next_deadline() {
+		emit(engine.tick(deadline), deadline, &mut out);
+	}
+	emit(Vec::new(), Duration::MAX, &mut out);
+	out
+}
+
+/// Hands out events as the wall clock reaches them, `speed` times faster
+/// than they were recorded. Event times count from `origin`, which
+/// [`Playback::new`]'s `start` stands for.
+pub struct Playback {
+	events: Vec<KeyEvent>,
+	next: usize,
+	origin: Duration,
+	start: Instant,
+	speed: f64,
+}
+
+impl Playback {
+	pub fn new(events: Vec<KeyEvent>, origin: Duration, speed: f64, start: Instant) -> Self {
+		Self {
+			events,
+			next: 0,
+			origin,
+			start,
+			speed,
+		}
+	}
+
+	fn at(&self, event: &KeyEvent) -> Instant {
+		self.start + event.time.saturating_sub(self.origin).div_f64(self.speed)
+	}
+
+	/// When the next event is due, `None` once all were handed out.
+	pub fn next_at(&self) -> Option<Instant> {
+		self.events.get(self.next).map(|event| self.at(event))
+	}
+
+	/// The events due by `now` and not handed out before.
+	pub fn due(&mut self, now: Instant) -> &[KeyEvent] {
+		let first = self.next;
+		while self
+			.events
+			.get(self.next)
+			.is_some_and(|event| self.at(event) <= now)
+		{
+			self.next += 1;
+		}
+		&self.events[first..self.next]
+	}
+
+	pub fn is_done(&self) -> bool {
+		self.next == self.events.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::Config;
+
+	fn key(code: KeyCode, state: KeyState, ms: u64) -> KeyEvent {
+		KeyEvent::new(code, state, Duration::from_millis(ms))
+	}
+
+	#[test]
+	fn reads_what_it_writes() {
+		let events = [
+			RawEvent {
+				time_us: 1_712_000_000_123_456,
+				kind: EventType::KEY.0,
+				code: KeyCode::KEY_CAPSLOCK.code(),
+				value: 1,
+			},
+			RawEvent {
+				time_us: 1_712_000_000_123_456,
+				kind: EventType::SYNCHRONIZATION.0,
+				code: 0,
+				value: 0,
+			},
+		];
+		let mut out = Vec::new();
+		write(&mut out, &events).unwrap();
+		assert_eq!(
+			String::from_utf8(out.clone()).unwrap().lines().next(),
+			Some(r#"{"time_us":1712000000123456,"type":1,"code":58,"value":1}"#)
+		);
+		out.extend_from_slice(b"\n");
+		assert_eq!(read(&out[..]).unwrap(), events);
+		assert_eq!(
+			events[0].key_event(),
+			Some(KeyEvent::new(
+				KeyCode::KEY_CAPSLOCK,
+				KeyState::Pressed,
+				Duration::from_micros(1_712_000_000_123_456)
+			))
+		);
+		assert_eq!(events[1].key_event(), None);
+
+		let error = read(&b"{}\n"[..]).unwrap_err();
+		assert_eq!(error.to_string(), "Invalid event on line 1");
+	}
+
+	#[test]
+	fn settles_holds_at_their_deadline() {
+		let config: Config = r#"
+name = "x"
+
+[[mappings]]
+original_key = "caps"
+tap = "Esc"
+hold = "LCtrl"
+"#
+		.parse()
+		.unwrap();
+		let keymap = Keymap::from_config(&config).unwrap();
+		let out = remap(
+			keymap,
+			&[
+				key(KeyCode::KEY_CAPSLOCK, KeyState::Pressed, 0),
+				key(KeyCode::KEY_CAPSLOCK, KeyState::Released, 1000),
+			],
+		);
+		assert_eq!(out.len(), 2);
+		assert_eq!(
+			(out[0].code, out[0].state),
+			(KeyCode::KEY_LEFTCTRL, KeyState::Pressed)
+		);
+		assert!(out[0].time < Duration::from_millis(1000));
+		assert_eq!(out[1], key(KeyCode::KEY_LEFTCTRL, KeyState::Released, 1000));
+	}
+
+	#[test]
+	fn keeps_the_order_events_are_emitted_in() {
+		let config: Config = r#"
+name = "x"
+
+[[mappings]]
+original_key = "A"
+tap = "A"
+hold = "LCtrl"
+"#
+		.parse()
+		.unwrap();
+		let keymap = Keymap::from_config(&config).unwrap();
+		let out = remap(
+			keymap,
+			&[
+				key(KeyCode::KEY_A, KeyState::Pressed, 0),
+				key(KeyCode::KEY_C, KeyState::Pressed, 50),
+				key(KeyCode::KEY_C, KeyState::Released, 80),
+				key(KeyCode::KEY_A, KeyState::Released, 100),
+			],
+		);
+		// C was held back behind A and only comes out after it
+		assert_eq!(
+			out,
+			vec![
+				key(KeyCode::KEY_A, KeyState::Pressed, 100),
+				key(KeyCode::KEY_A, KeyState::Released, 100),
+				key(KeyCode::KEY_C, KeyState::Pressed, 50),
+				key(KeyCode::KEY_C, KeyState::Released, 80),
+			]
+		);
+	}
+
+	#[test]
+	fn plays_back_faster() {
+		let start = Instant::now();
+		let origin = Duration::from_secs(100);
+		let events = vec![
+			key(KeyCode::KEY_A, KeyState::Pressed, 100_000),
+			key(KeyCode::KEY_A, KeyState::Released, 100_200),
+		];
+		let mut playback = Playback::new(events.clone(), origin, 2.0, start);
+		assert_eq!(playback.next_at(), Some(start));
+		assert_eq!(playback.due(start), &events[..1]);
+		assert_eq!(playback.next_at(), Some(start + Duration::from_millis(100)));
+		assert!(playback.due(start + Duration::from_millis(99)).is_empty());
+		assert_eq!(
+			playback.due(start + Duration::from_millis(100)),
+			&events[1..]
+		);
+		assert!(playback.is_done());
+	}
+}
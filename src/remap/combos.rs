@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use evdev::KeyCode;
+
+use super::keymap::ComboBinding;
+use super::{KeyEvent, KeyState};
+
+/// What the combo stage hands on to the rest of the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Step {
+	Key(KeyEvent),
+	/// Combo `combo` fired; its target goes down as if `key` was pressed.
+	Press {
+		combo: usize,
+		key: KeyCode,
+		time: Duration,
+	},
+	/// The combo held under `key` ended.
+	Release {
+		key: KeyCode,
+		time: Duration,
+	},
+}
+
+impl Step {
+	pub fn code(&self) -> KeyCode {
+		match self {
+			Step::Key(event) => event.code,
+			Step::Press { key, .. } | Step::Release { key, .. } => *key,
+		}
+	}
+
+	pub fn state(&self) -> KeyState {
+		match self {
+			Step::Key(event) => event.state,
+			Step::Press { .. } => KeyState::Pressed,
+			Step::Release { .. } => KeyState::Released,
+		}
+	}
+
+	pub fn time(&self) -> Duration {
+		match self {
+			Step::Key(event) => event.time,
+			Step::Press { time, .. } | Step::Release { time, .. } => *time,
+		}
+	}
+}
+
+/// Presses of combo keys held back until we know whether they complete a
+/// combo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pending {
+	started: Duration,
+	pressed: Vec<KeyCode>,
+	buffered: Vec<KeyEvent>,
+}
+
+/// A fired combo whose keys are not all released yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Active {
+	key: KeyCode,
+	down: Vec<KeyCode>,
+	released: bool,
+}
+
+/// Buffers presses of combo keys for at most the combo term.
+///
+/// Only presses that can still complete a combo are held back; any other
+/// event ends the wait and is emitted after the buffered ones, so events
+/// always leave this stage in the order they arrived.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(super) struct ComboStage {
+	pending: Option<Pending>,
+	active: Vec<Active>,
+}
+
+impl ComboStage {
+	pub fn feed(&mut self, combos: &[ComboBinding], event: KeyEvent, out: &mut Vec<Step>) {
+		// Keys of a fired combo are swallowed; the first release ends it.
+		if let Some(idx) = self
+			.active
+			.iter()
+			.position(|active| active.down.contains(&event.code))
+		{
+			if event.state == KeyState::Released {
+				let active = &mut self.active[idx];
+				active.down.retain(|code| *code != event.code);
+				if !active.released {
+					active.released = true;
+					out.push(Step::Release {
+						key: active.key,
+						time: event.time,
+					});
+				}
+				if active.down.is_empty() {
+					self.active.remove(idx);
+				}
+			}
+			return;
+		}
+
+		if let Some(pending) = self.pending.as_mut() {
+			if event.state == KeyState::Pressed && !pending.pressed.contains(&event.code) {
+				let mut pressed = pending.pressed.clone();
+				pressed.push(event.code);
+				if candidates(combos, &pressed).next().is_some() {
+					pending.pressed = pressed;
+					pending.buffered.push(event);
+					// Fire right away unless a longer combo could still match.
+					if candidates(combos, &pending.pressed).count() == 1 {
+						if let Some(combo) = exact(combos, &pending.pressed) {
+							self.fire(combos, combo, event.time, out);
+						}
+					}
+					return;
+				}
+			}
+
+			self.settle(combos, out);
+			self.feed(combos, event, out);
+			return;
+		}
+
+		if event.state == KeyState::Pressed
+			&& combos.iter().any(|combo| combo.keys.contains(&event.code))
+		{
+			self.pending = Some(Pending {
+				started: event.time,
+				pressed: vec![event.code],
+				buffered: vec![event],
+			});
+			return;
+		}
+
+		out.push(Step::Key(event));
+	}
+
+	/// When the buffered presses must be settled, if any are buffered.
+	pub fn deadline(&self, combos: &[ComboBinding]) -> Option<Duration> {
+		let pending = self.pending.as_ref()?;
+		let term = candidates(combos, &pending.pressed)
+			.map(|combo| combos[combo].term)
+			.max()
+			.unwrap_or_default();
+		Some(pending.started + term)
+	}
+
+	/// The combo keys held back so far, if any.
+	pub fn pressed(&self) -> Option<&[KeyCode]> {
+		self
+			.pending
+			.as_ref()
+			.map(|pending| pending.pressed.as_slice())
+	}
+
+	/// Fires the combo the buffered presses complete, or lets them through
+	/// as ordinary key presses.
+	pub fn settle(&mut self, combos: &[ComboBinding], out: &mut Vec<Step>) {
+		let Some(pending) = self.pending.as_ref() else {
+			return;
+		};
+		match exact(combos, &pending.pressed) {
+			Some(combo) => {
+				let time = pending
+					.buffered
+					.last()
+					.map_or(pending.started, |event| event.time);
+				self.fire(combos, combo, time, out);
+			}
+			None => {
+				if let Some(pending) = self.pending.take() {
+					out.extend(pending.buffered.into_iter().map(Step::Key));
+				}
+			}
+		}
+	}
+
+	pub fn reset(&mut self) {
+		self.pending = None;
+		self.active.clear();
+	}
+
+	fn fire(&mut self, combos: &[ComboBinding], combo: usize, time: Duration, out: &mut Vec<Step>) {
+		let Some(pending) = self.pending.take() else {
+			return;
+		};
+		let key = combos[combo].keys[0];
+		self.active.push(Active {
+			key,
+			down: pending.pressed,
+			released: false,
+		});
+		out.push(Step::Press { combo, key, time });
+	}
+}
+
+/// Combos that contain every key in `pressed`.
+fn candidates<'a>(
+	combos: &'a [ComboBinding],
+	pressed: &'a [KeyCode],
+) -> impl Iterator<Item = usize> + 'a {
+	combos
+		.iter()
+		.enumerate()
+		.filter(|(_, combo)| pressed.iter().all(|code| combo.keys.contains(code)))
+		.map(|(idx, _)| idx)
+}
+
+/// The combo made of exactly the keys in `pressed`.
+fn exact(combos: &[ComboBinding], pressed: &[KeyCode]) -> Option<usize> {
+	candidates(combos, pressed).find(|idx| combos[*idx].keys.len() == pressed.len())
+}
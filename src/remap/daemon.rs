@@ -0,0 +1,381 @@
+use std::collections::HashSet;
+use std::mem;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use evdev::{Device, EventType, InputEvent, KeyCode, MiscCode};
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::signal::{SigSet, Signal};
+use nix::sys::signalfd::{SfdFlags, SignalFd};
+
+use super::reload::{self, ConfigWatch};
+use super::status::{self, StatusServer};
+use super::{Engine, KeyEvent, KeyState, Undecided, VirtualKeyboard};
+use crate::config::{Config, StatsConfig};
+use crate::device::{self, DeviceInfo, EvdevProbe};
+use crate::keys;
+use crate::stats::{Recorder, Stats, StatsLock};
+
+/// How long to wait for keys held at startup (usually the Enter that
+/// launched us) to be released before grabbing the device.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Grabs the configured keyboard and forwards its remapped events to a
+/// virtual keyboard until the panic chord is held, the process is told to
+/// stop (Ctrl+C, SIGTERM, a closed terminal) or an I/O error occurs.
+/// The config is refused on any error `validate` finds, and edits to it
+/// are applied as they are saved.
+pub fn run(config_path: &Path, device_path: Option<&Path>) -> Result<()> {
+	let (config, keymap, mut panic) = reload::load(config_path)?;
+
+	let info = find_device(&config, device_path)?;
+	let mut device = open(&info.path)?;
+	let mut output = VirtualKeyboard::create(Some(&device), keymap.targets())?;
+
+	wait_for_release(&device)?;
+	device
+		.grab()
+		.with_context(|| format!("Cannot grab {}", info.path.display()))?;
+
+	eprintln!(
+		"Remapping {} ({}) with {} mapping(s), press Ctrl+C or hold {} to stop",
+		info.name,
+		info.path.display(),
+		config.mappings.len(),
+		panic.describe()
+	);
+
+	let mut status = match status::socket_path().map(|path| StatusServer::bind(&path)) {
+		Some(Ok(server)) => Some(server),
+		Some(Err(err)) => {
+			eprintln!("{:#}, not reporting the active layer", err);
+			None
+		}
+		None => {
+			eprintln!("No runtime directory for the status socket, not reporting the active layer");
+			None
+		}
+	};
+
+	let mut watch = match ConfigWatch::new(config_path) {
+		Ok(watch) => Some(watch),
+		Err(err) => {
+			eprintln!("{:#}, restart to apply changes to the config", err);
+			None
+		}
+	};
+
+	let mut stats = config.stats;
+	let mut recorder = stats.as_ref().and_then(record);
+
+	let signals = stop_signals()?;
+	let mut engine = Engine::new(keymap);
+	// Keys held through a reload, already released on the virtual keyboard
+	// and ignored until they come up
+	let mut stale: HashSet<KeyCode> = HashSet::new();
+	loop {
+		if let Some(status) = status.as_mut() {
+			status.publish(engine.keymap().layer_name(engine.active_layer()));
+			status.accept();
+		}
+
+		let timeout = engine
+			.next_deadline()
+			.into_iter()
+			.chain(output.next_deadline())
+			.chain(panic.deadline())
+			.chain(recorder.as_ref().and_then(Recorder::deadline))
+			.min()
+			.map(|deadline| deadline.saturating_sub(now()));
+
+		let watched: Vec<BorrowedFd> = [signals.as_fd()]
+			.into_iter()
+			.chain(status.iter().map(AsFd::as_fd))
+			.chain(watch.iter().map(AsFd::as_fd))
+			.collect();
+		let mut forwarded = Vec::new();
+		if wait_readable(&device, &watched, timeout)? {
+			let now = now();
+			let mut scan = Vec::new();
+			for event in device
+				.fetch_events()
+				.with_context(|| format!("Cannot read from {}", info.path.display()))?
+			{
+				if let Some(event) = KeyEvent::from_input(&event) {
+					let scan = mem::take(&mut scan);
+					panic.feed(&event);
+					if stale.contains(&event.code) {
+						if event.state == KeyState::Released {
+							stale.remove(&event.code);
+						}
+						continue;
+					}
+					if let Some(recorder) = recorder
+						.as_mut()
+						.filter(|_| event.state == KeyState::Pressed)
+					{
+						let layer = engine.keymap().layer_name(engine.active_layer());
+						recorder.press(&keys::name_of(event.code), layer, event.time);
+					}
+					output.emit_scanned(&engine.process(event), &scan, now)?;
+				} else if is_scan(&event) {
+					scan.push(event);
+				} else if passes_through(&event) {
+					forwarded.push(event);
+				}
+			}
+		}
+		let now = now();
+		let stop = if panic.fired(now) {
+			Some("Panic chord held".to_string())
+		} else if let Ok(Some(signal)) = signals.read_signal() {
+			let name = Signal::try_from(signal.ssi_signo as i32).map_or("Signal", Signal::as_str);
+			Some(format!("{} received", name))
+		} else {
+			None
+		};
+		if let Some(reason) = stop {
+			if let Some(Err(err)) = recorder.as_mut().map(|recorder| recorder.flush(now)) {
+				eprintln!("{:#}", err);
+			}
+			output.emit(&engine.release_all(now), now)?;
+			output.release_all(now)?;
+			device
+				.ungrab()
+				.with_context(|| format!("Cannot ungrab {}", info.path.display()))?;
+			eprintln!("{}, released {}", reason, info.path.display());
+			return Ok(());
+		}
+		output.emit(&engine.tick(now), now)?;
+		output.forward(&forwarded)?;
+
+		let changed = match watch.as_ref().map(ConfigWatch::changed) {
+			Some(Ok(changed)) => changed,
+			Some(Err(err)) => {
+				eprintln!("{:#}, restart to apply changes to the config", err);
+				watch = None;
+				false
+			}
+			None => false,
+		};
+		if let Some(watch) = watch.as_ref().filter(|_| changed) {
+			match reload::load(watch.path()) {
+				Ok((config, keymap, chord)) => {
+					// Nothing held under the old mappings may stick. Keys still
+					// undecided are forgotten too, and their release may be
+					// read after the key state says they are up.
+					stale = engine
+						.undecided()
+						.iter()
+						.flat_map(Undecided::keys)
+						.collect();
+					output.emit(&engine.release_all(now), now)?;
+					output.release_all(now)?;
+					stale.extend(
+						device
+							.get_key_state()
+							.with_context(|| format!("Cannot read from {}", info.path.display()))?
+							.iter(),
+					);
+					let mut missing: Vec<String> = keymap
+						.targets()
+						.filter(|code| !output.supports(*code))
+						.map(keys::name_of)
+						.collect();
+					missing.sort();
+					missing.dedup();
+					if !missing.is_empty() {
+						eprintln!(
+							"Cannot type {} until restarting, the virtual keyboard lacks them",
+							missing.join(", ")
+						);
+					}
+					engine = Engine::new(keymap);
+					panic = chord;
+					if config.stats != stats {
+						// Let go of the file first, it may be the same one
+						if let Some(Err(err)) = recorder.take().map(|mut recorder| recorder.flush(now)) {
+							eprintln!("{:#}", err);
+						}
+						stats = config.stats;
+						recorder = stats.as_ref().and_then(record);
+						if stats.is_none() {
+							eprintln!("No longer counting presses");
+						}
+					}
+					eprintln!(
+						"Reloaded {} with {} mapping(s), hold {} to stop",
+						watch.path().display(),
+						config.mappings.len(),
+						panic.describe()
+					);
+				}
+				Err(err) => eprintln!("{:#}\nKeeping the previous config", err),
+			}
+		}
+
+		let due = recorder
+			.as_mut()
+			.filter(|recorder| recorder.deadline().is_some_and(|deadline| deadline <= now));
+		if let Some(Err(err)) = due.map(|recorder| recorder.flush(now)) {
+			eprintln!("{:#}, no longer counting presses", err);
+			recorder = None;
+		}
+	}
+}
+
+/// Starts counting presses as `stats` asks, saying where or why not.
+fn record(stats: &StatsConfig) -> Option<Recorder> {
+	let Some(path) = stats.file.clone().or_else(Stats::default_path) else {
+		eprintln!("No home directory for the stats file, not counting presses");
+		return None;
+	};
+	match StatsLock::acquire(&path, Duration::ZERO) {
+		Ok(Some(file)) => {
+			eprintln!("Counting presses in {}", path.display());
+			Some(Recorder::new(file, stats.bigrams, now()))
+		}
+		Ok(None) => {
+			eprintln!(
+				"{} is in use by another process, not counting presses",
+				path.display()
+			);
+			None
+		}
+		Err(err) => {
+			eprintln!("{:#}, not counting presses", err);
+			None
+		}
+	}
+}
+
+/// The device at `device_path`, or the one the config selects.
+pub(super) fn find_device(config: &Config, device_path: Option<&Path>) -> Result<DeviceInfo> {
+	match device_path {
+		Some(path) => {
+			let device = open(path)?;
+			Ok(DeviceInfo::from_device(path.to_path_buf(), &device))
+		}
+		None => device::select(&EvdevProbe, config.device.as_ref()),
+	}
+}
+
+/// The clock evdev timestamps events with.
+pub(super) fn now() -> Duration {
+	SystemTime::now()
+		.duration_since(SystemTime::UNIX_EPOCH)
+		.unwrap_or_default()
+}
+
+/// Blocks until the device has events, one of the `others` is readable
+/// or `timeout` elapses. Returns whether the device has events.
+pub(super) fn wait_readable(
+	device: &Device,
+	others: &[BorrowedFd],
+	timeout: Option<Duration>,
+) -> Result<bool> {
+	let timeout = match timeout {
+		// Round up so we never wake just before a deadline.
+		Some(timeout) => {
+			PollTimeout::try_from(timeout + Duration::from_micros(999)).unwrap_or(PollTimeout::MAX)
+		}
+		None => PollTimeout::NONE,
+	};
+	let mut fds = vec![PollFd::new(device.as_fd(), PollFlags::POLLIN)];
+	fds.extend(others.iter().map(|fd| PollFd::new(*fd, PollFlags::POLLIN)));
+	match poll(&mut fds, timeout) {
+		// Errors and hang-ups count too, so reading reports them
+		Ok(_) => Ok(fds[0].revents().is_some_and(|events| !events.is_empty())),
+		Err(Errno::EINTR) => Ok(false),
+		Err(err) => Err(err).context("Cannot wait for input events"),
+	}
+}
+
+pub(super) fn open(path: &Path) -> Result<Device> {
+	Device::open(path).with_context(|| {
+		format!(
+			"Cannot open {} (is this user in the `input` group?)",
+			path.display()
+		)
+	})
+}
+
+/// Whether an event of the grabbed device goes to
+/// [`VirtualKeyboard::forward`]: everything but keys, which are remapped,
+/// and their scancodes, which are written with them.
+/// Synchronisation marks where reports end. LEDs are set by the desktop
+/// rather than typed, so they stay with the device.
+fn passes_through(event: &InputEvent) -> bool {
+	!matches!(event.event_type(), EventType::KEY | EventType::LED) && !is_scan(event)
+}
+
+/// Whether `event` is the scancode a keyboard sends just before a key
+/// event, in the same report.
+fn is_scan(event: &InputEvent) -> bool {
+	event.event_type() == EventType::MISC && event.code() == MiscCode::MSC_SCAN.0
+}
+
+/// The signals that stop the daemon, read from a descriptor rather than
+/// handled, so stopping releases the keyboard and flushes counts just like
+/// the panic chord.
+fn stop_signals() -> Result<SignalFd> {
+	let mut mask = SigSet::empty();
+	for signal in [Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP] {
+		mask.add(signal);
+	}
+	mask
+		.thread_block()
+		.context("Cannot block the stopping signals")?;
+	SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)
+		.context("Cannot watch for stopping signals")
+}
+
+fn wait_for_release(device: &Device) -> Result<()> {
+	let started = Instant::now();
+	while started.elapsed() < RELEASE_TIMEOUT {
+		if device.get_key_state()?.iter().next().is_none() {
+			break;
+		}
+		thread::sleep(Duration::from_millis(10));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use evdev::RelativeAxisCode;
+
+	use super::*;
+
+	#[test]
+	fn passes_through_all_but_keys() {
+		let motion = InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, 5);
+		let scancode = InputEvent::new(EventType::MISC.0, MiscCode::MSC_SCAN.0, 0x70039);
+		assert!(passes_through(&motion));
+		assert!(!passes_through(&scancode));
+		assert!(is_scan(&scancode));
+		assert!(passes_through(&InputEvent::new(
+			EventType::SYNCHRONIZATION.0,
+			0,
+			0
+		)));
+		assert!(!passes_through(
+			&KeyEvent::new(KeyCode::KEY_A, KeyState::Pressed, Duration::ZERO).to_input()
+		));
+		assert!(!passes_through(&InputEvent::new(EventType::LED.0, 1, 1)));
+	}
+
+	#[test]
+	fn reads_stopping_signals() {
+		let signals = stop_signals().unwrap();
+		assert!(signals.read_signal().unwrap().is_none());
+		// Sent to this thread alone, which now blocks it
+		nix::sys::signal::raise(Signal::SIGTERM).unwrap();
+		let signal = signals.read_signal().unwrap().unwrap();
+		assert_eq!(signal.ssi_signo, Signal::SIGTERM as u32);
+	}
+}
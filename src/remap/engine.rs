@@ -0,0 +1,911 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use evdev::{EventType, InputEvent, KeyCode};
+
+use super::combos::{ComboStage, Step};
+use super::keymap::{Binding, LayerId, LayerSwitch, MacroId, MacroOp, Target};
+use super::layers::LayerStack;
+use super::tap_hold::{Decision, PendingTapHold};
+use super::Keymap;
+
+/// Key event values as reported by evdev.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyState {
+	Released,
+	Pressed,
+	Repeat,
+}
+
+impl KeyState {
+	pub fn from_value(value: i32) -> Option<Self> {
+		match value {
+			0 => Some(KeyState::Released),
+			1 => Some(KeyState::Pressed),
+			2 => Some(KeyState::Repeat),
+			_ => None,
+		}
+	}
+
+	pub fn value(self) -> i32 {
+		match self {
+			KeyState::Released => 0,
+			KeyState::Pressed => 1,
+			KeyState::Repeat => 2,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			KeyState::Released => "up",
+			KeyState::Pressed => "down",
+			KeyState::Repeat => "repeat",
+		}
+	}
+}
+
+/// A key event flowing through the engine. `time` is only ever compared
+/// with other event times, so tests can use any origin they like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+	pub code: KeyCode,
+	pub state: KeyState,
+	pub time: Duration,
+}
+
+impl KeyEvent {
+	pub fn new(code: KeyCode, state: KeyState, time: Duration) -> Self {
+		Self { code, state, time }
+	}
+
+	/// Converts an evdev `EV_KEY` event, ignoring every other event type.
+	pub fn from_input(event: &InputEvent) -> Option<Self> {
+		if event.event_type() != EventType::KEY {
+			return None;
+		}
+		let time = event
+			.timestamp()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap_or_default();
+		KeyState::from_value(event.value())
+			.map(|state| Self::new(KeyCode::new(event.code()), state, time))
+	}
+
+	pub fn to_input(self) -> InputEvent {
+		InputEvent::new(EventType::KEY.0, self.code.code(), self.state.value())
+	}
+
+	fn with_code(self, code: KeyCode) -> Self {
+		Self { code, ..self }
+	}
+}
+
+/// A decision the engine is holding events back for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Undecided {
+	/// A dual-role key that is down, with the events buffered behind it.
+	TapHold {
+		key: KeyCode,
+		deadline: Duration,
+		buffered: usize,
+	},
+	/// Presses that may still complete a combo.
+	Combo {
+		keys: Vec<KeyCode>,
+		deadline: Duration,
+	},
+}
+
+impl Undecided {
+	/// The physical keys down while this is undecided.
+	pub fn keys(&self) -> Vec<KeyCode> {
+		match self {
+			Undecided::TapHold { key, .. } => vec![*key],
+			Undecided::Combo { keys, .. } => keys.clone(),
+		}
+	}
+}
+
+/// Turns physical key events into the events the virtual keyboard emits.
+///
+/// The engine does no I/O and never reads the clock: time only advances
+/// through event timestamps and [`Engine::tick`], so the whole pipeline can
+/// be driven from a list of events in tests.
+pub struct Engine {
+	keymap: Keymap,
+	combos: ComboStage,
+	layers: LayerStack,
+	/// What each physically held key was turned into when it went down, so
+	/// repeats and the release always match the press even if the active
+	/// layers changed in between.
+	held: HashMap<KeyCode, Held>,
+	pending: Option<PendingTapHold>,
+	/// When the last macro finishes playing, so macros never interleave.
+	macro_end: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Held {
+	Key(KeyCode),
+	Layer(LayerSwitch),
+	Macro,
+}
+
+impl Engine {
+	pub fn new(keymap: Keymap) -> Self {
+		Self {
+			keymap,
+			combos: ComboStage::default(),
+			layers: LayerStack::default(),
+			held: HashMap::new(),
+			pending: None,
+			macro_end: Duration::ZERO,
+		}
+	}
+
+	pub fn keymap(&self) -> &Keymap {
+		&self.keymap
+	}
+
+	/// The topmost active layer.
+	pub fn active_layer(&self) -> LayerId {
+		self.layers.top()
+	}
+
+	pub fn process(&mut self, event: KeyEvent) -> Vec<KeyEvent> {
+		let mut out = self.tick(event.time);
+		let mut steps = Vec::new();
+		self.combos.feed(self.keymap.combos(), event, &mut steps);
+		for step in steps {
+			self.handle(step, &mut out);
+		}
+		out
+	}
+
+	/// Runs a whole event stream through the engine.
+	pub fn process_all<I>(&mut self, events: I) -> Vec<KeyEvent>
+	where
+		I: IntoIterator<Item = KeyEvent>,
+	{
+		events
+			.into_iter()
+			.flat_map(|event| self.process(event))
+			.collect()
+	}
+
+	/// Resolves every decision whose deadline is at or before `now`.
+	pub fn tick(&mut self, now: Duration) -> Vec<KeyEvent> {
+		let mut out = Vec::new();
+		loop {
+			let combo = self.combos.deadline(self.keymap.combos());
+			let tap_hold = self.pending.as_ref().map(PendingTapHold::deadline);
+
+			match (combo, tap_hold) {
+				(Some(combo), tap_hold) if combo <= now && tap_hold.is_none_or(|t| combo <= t) => {
+					let mut steps = Vec::new();
+					self.combos.settle(self.keymap.combos(), &mut steps);
+					for step in steps {
+						self.handle(step, &mut out);
+					}
+				}
+				(_, Some(deadline)) if deadline <= now => {
+					if let Some(pending) = self.pending.take() {
+						self.resolve(pending, Decision::Hold, deadline, &mut out);
+					}
+				}
+				_ => return out,
+			}
+		}
+	}
+
+	/// When [`Engine::tick`] next needs to be called, if at all.
+	pub fn next_deadline(&self) -> Option<Duration> {
+		let combo = self.combos.deadline(self.keymap.combos());
+		let tap_hold = self.pending.as_ref().map(PendingTapHold::deadline);
+		combo.into_iter().chain(tap_hold).min()
+	}
+
+	/// What the engine is waiting to decide, combos first as they come
+	/// first in the pipeline.
+	pub fn undecided(&self) -> Vec<Undecided> {
+		let combo = self
+			.combos
+			.pressed()
+			.zip(self.combos.deadline(self.keymap.combos()))
+			.map(|(keys, deadline)| Undecided::Combo {
+				keys: keys.to_vec(),
+				deadline,
+			});
+		let tap_hold = self.pending.as_ref().map(|pending| Undecided::TapHold {
+			key: pending.key,
+			deadline: pending.deadline(),
+			buffered: pending.buffered.len(),
+		});
+		combo.into_iter().chain(tap_hold).collect()
+	}
+
+	/// Releases every key the engine is currently holding down, forgets
+	/// any undecided dual-role key and returns to the base layer.
+	pub fn release_all(&mut self, time: Duration) -> Vec<KeyEvent> {
+		self.pending = None;
+		self.combos.reset();
+		self.layers.clear();
+		self
+			.held
+			.drain()
+			.filter_map(|(_, held)| match held {
+				Held::Key(code) => Some(KeyEvent::new(code, KeyState::Released, time)),
+				Held::Layer(_) | Held::Macro => None,
+			})
+			.collect()
+	}
+
+	fn handle(&mut self, step: Step, out: &mut Vec<KeyEvent>) {
+		if let Some(pending) = self.pending.as_mut() {
+			let decision = pending.feed(step);
+			if decision != Decision::Undecided {
+				if let Some(pending) = self.pending.take() {
+					self.resolve(pending, decision, step.time(), out);
+				}
+			}
+			return;
+		}
+
+		let event = match step {
+			Step::Key(event) => event,
+			Step::Press { combo, key, time } => {
+				if let Some(combo) = self.keymap.combos().get(combo) {
+					self.press(key, combo.target, time, out);
+				}
+				return;
+			}
+			Step::Release { key, time } => return self.release(key, time, out),
+		};
+
+		match event.state {
+			KeyState::Pressed => match self.keymap.binding(event.code, self.layers.iter()) {
+				Binding::Target(target) => self.press(event.code, target, event.time, out),
+				Binding::TapHold(binding) => {
+					self.pending = Some(PendingTapHold::new(event.code, binding, event.time));
+				}
+				Binding::Transparent => self.press(event.code, Target::Key(event.code), event.time, out),
+			},
+			KeyState::Repeat => match self.held.get(&event.code) {
+				Some(Held::Key(code)) => out.push(event.with_code(*code)),
+				Some(Held::Layer(_) | Held::Macro) => {}
+				None => out.push(event),
+			},
+			KeyState::Released => self.release(event.code, event.time, out),
+		}
+	}
+
+	/// Applies `target` for the physical key `key` going down.
+	fn press(&mut self, key: KeyCode, target: Target, time: Duration, out: &mut Vec<KeyEvent>) {
+		match target {
+			Target::Key(code) => {
+				self.layers.consume_one_shot();
+				self.held.insert(key, Held::Key(code));
+				out.push(KeyEvent::new(code, KeyState::Pressed, time));
+			}
+			Target::Layer(switch) => {
+				self.layers.press(switch, key);
+				self.held.insert(key, Held::Layer(switch));
+			}
+			Target::Macro(id) => {
+				self.layers.consume_one_shot();
+				self.held.insert(key, Held::Macro);
+				self.play(id, time, out);
+			}
+		}
+	}
+
+	/// Expands a macro into events stamped with when they are due; the
+	/// output stage holds back the ones after a delay.
+	fn play(&mut self, id: MacroId, time: Duration, out: &mut Vec<KeyEvent>) {
+		let Some(binding) = self.keymap.macro_binding(id) else {
+			return;
+		};
+		let mut time = time.max(self.macro_end);
+		for op in &binding.ops {
+			match op {
+				MacroOp::Tap { mods, key } => {
+					let event = |code, state| KeyEvent::new(code, state, time);
+					out.extend(mods.iter().map(|code| event(*code, KeyState::Pressed)));
+					out.push(event(*key, KeyState::Pressed));
+					out.push(event(*key, KeyState::Released));
+					out.extend(
+						mods
+							.iter()
+							.rev()
+							.map(|code| event(*code, KeyState::Released)),
+					);
+				}
+				MacroOp::Delay(delay) => time += *delay,
+			}
+		}
+		self.macro_end = time;
+	}
+
+	/// Undoes whatever the physical key `key` did when it went down.
+	fn release(&mut self, key: KeyCode, time: Duration, out: &mut Vec<KeyEvent>) {
+		match self.held.remove(&key) {
+			Some(Held::Key(code)) => out.push(KeyEvent::new(code, KeyState::Released, time)),
+			Some(Held::Layer(switch)) => self.layers.release(switch, key),
+			Some(Held::Macro) => {}
+			None => out.push(KeyEvent::new(key, KeyState::Released, time)),
+		}
+	}
+
+	fn resolve(
+		&mut self,
+		pending: PendingTapHold,
+		decision: Decision,
+		time: Duration,
+		out: &mut Vec<KeyEvent>,
+	) {
+		match decision {
+			Decision::Tap => {
+				self.press(pending.key, pending.binding.tap, time, out);
+				self.release(pending.key, time, out);
+			}
+			Decision::Hold => self.press(pending.key, pending.binding.hold, time, out),
+			Decision::Undecided => return,
+		}
+
+		for step in pending.buffered {
+			self.handle(step, out);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::Config;
+
+	const CONFIG: &str = r#"
+name = "test"
+
+[[mappings]]
+original_key = "CapsLock"
+target_key = "Escape"
+
+[[mappings]]
+original_key = "Escape"
+target_key = "CapsLock"
+
+[[mappings]]
+original_key = "A"
+tap = "A"
+hold = "LeftCtrl"
+
+[[mappings]]
+original_key = "S"
+tap = "S"
+hold = "LeftShift"
+permissive_hold = true
+
+[[mappings]]
+original_key = "D"
+tap = "D"
+hold = "LeftAlt"
+tapping_term = 300
+hold_on_other_key_press = true
+
+[[mappings]]
+original_key = "Space"
+tap = "Space"
+hold = "MO(nav)"
+
+[[mappings]]
+original_key = "F1"
+target_key = "TG(numpad)"
+
+[[mappings]]
+original_key = "F2"
+target_key = "OSL(nav)"
+
+[[combos]]
+keys = ["J", "K"]
+target = "Escape"
+
+[[combos]]
+keys = ["X", "C"]
+target = "LeftMeta"
+
+[[combos]]
+keys = ["X", "C", "V"]
+target = "F13"
+timeout = 80
+
+[[mappings]]
+original_key = "F3"
+target_key = "MACRO(arrow)"
+
+[[mappings]]
+original_key = "F4"
+target_key = "MACRO(paste)"
+
+[macros]
+arrow = [{ text = "-> " }]
+paste = ["Ctrl+Shift+V", { delay = 100 }, "Enter"]
+
+[layers.nav]
+H = "Left"
+J = "Down"
+F1 = "TO(numpad)"
+Q = "_"
+
+[layers.numpad]
+J = "Kp4"
+K = "Kp5"
+F2 = "TO(base)"
+"#;
+
+	fn engine() -> Engine {
+		let config: Config = CONFIG.parse().unwrap();
+		Engine::new(Keymap::from_config(&config).unwrap())
+	}
+
+	fn ev(code: KeyCode, state: KeyState, ms: u64) -> KeyEvent {
+		KeyEvent::new(code, state, Duration::from_millis(ms))
+	}
+
+	#[test]
+	fn swaps_keys_including_repeat_and_release() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_CAPSLOCK, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_CAPSLOCK, KeyState::Repeat, 500),
+			ev(KeyCode::KEY_ESC, KeyState::Pressed, 510),
+			ev(KeyCode::KEY_CAPSLOCK, KeyState::Released, 520),
+			ev(KeyCode::KEY_ESC, KeyState::Released, 530),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_ESC, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_ESC, KeyState::Repeat, 500),
+				ev(KeyCode::KEY_CAPSLOCK, KeyState::Pressed, 510),
+				ev(KeyCode::KEY_ESC, KeyState::Released, 520),
+				ev(KeyCode::KEY_CAPSLOCK, KeyState::Released, 530),
+			]
+		);
+	}
+
+	#[test]
+	fn passes_unmapped_keys_through() {
+		let events = [
+			ev(KeyCode::KEY_Q, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_Q, KeyState::Released, 10),
+		];
+		assert_eq!(engine().process_all(events), events);
+	}
+
+	#[test]
+	fn release_all_releases_remapped_targets() {
+		let mut engine = engine();
+		engine.process(ev(KeyCode::KEY_CAPSLOCK, KeyState::Pressed, 0));
+		engine.process(ev(KeyCode::KEY_A, KeyState::Pressed, 1));
+		let undecided: Vec<KeyCode> = engine
+			.undecided()
+			.iter()
+			.flat_map(Undecided::keys)
+			.collect();
+		assert_eq!(undecided, vec![KeyCode::KEY_A]);
+
+		assert_eq!(
+			engine.release_all(Duration::from_millis(5)),
+			vec![ev(KeyCode::KEY_ESC, KeyState::Released, 5)]
+		);
+		assert!(engine.undecided().is_empty());
+		assert!(engine.release_all(Duration::ZERO).is_empty());
+	}
+
+	#[test]
+	fn quick_release_is_a_tap() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_A, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_A, KeyState::Repeat, 100),
+			ev(KeyCode::KEY_A, KeyState::Released, 150),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_A, KeyState::Pressed, 150),
+				ev(KeyCode::KEY_A, KeyState::Released, 150),
+			]
+		);
+	}
+
+	#[test]
+	fn tapping_term_expiry_is_a_hold() {
+		let mut engine = engine();
+		assert!(engine
+			.process(ev(KeyCode::KEY_A, KeyState::Pressed, 0))
+			.is_empty());
+		assert_eq!(engine.next_deadline(), Some(Duration::from_millis(200)));
+		assert!(engine.tick(Duration::from_millis(199)).is_empty());
+		assert_eq!(
+			engine.tick(Duration::from_millis(250)),
+			vec![ev(KeyCode::KEY_LEFTCTRL, KeyState::Pressed, 200)]
+		);
+		assert_eq!(
+			engine.process(ev(KeyCode::KEY_A, KeyState::Released, 400)),
+			vec![ev(KeyCode::KEY_LEFTCTRL, KeyState::Released, 400)]
+		);
+	}
+
+	#[test]
+	fn late_event_resolves_hold_before_itself() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_A, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_C, KeyState::Pressed, 300),
+			ev(KeyCode::KEY_C, KeyState::Released, 320),
+			ev(KeyCode::KEY_A, KeyState::Released, 340),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_LEFTCTRL, KeyState::Pressed, 200),
+				ev(KeyCode::KEY_C, KeyState::Pressed, 300),
+				ev(KeyCode::KEY_C, KeyState::Released, 320),
+				ev(KeyCode::KEY_LEFTCTRL, KeyState::Released, 340),
+			]
+		);
+	}
+
+	#[test]
+	fn nested_tap_defaults_to_tap() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_A, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_C, KeyState::Pressed, 50),
+			ev(KeyCode::KEY_C, KeyState::Released, 80),
+			ev(KeyCode::KEY_A, KeyState::Released, 100),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_A, KeyState::Pressed, 100),
+				ev(KeyCode::KEY_A, KeyState::Released, 100),
+				ev(KeyCode::KEY_C, KeyState::Pressed, 50),
+				ev(KeyCode::KEY_C, KeyState::Released, 80),
+			]
+		);
+	}
+
+	#[test]
+	fn permissive_hold_on_nested_tap() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_S, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_C, KeyState::Pressed, 50),
+			ev(KeyCode::KEY_C, KeyState::Released, 80),
+			ev(KeyCode::KEY_S, KeyState::Released, 100),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Pressed, 80),
+				ev(KeyCode::KEY_C, KeyState::Pressed, 50),
+				ev(KeyCode::KEY_C, KeyState::Released, 80),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Released, 100),
+			]
+		);
+	}
+
+	#[test]
+	fn permissive_hold_ignores_rolled_keys() {
+		// C was already down before S, so releasing it is a roll, not a tap
+		// inside S.
+		let out = engine().process_all([
+			ev(KeyCode::KEY_C, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_S, KeyState::Pressed, 10),
+			ev(KeyCode::KEY_C, KeyState::Released, 30),
+			ev(KeyCode::KEY_S, KeyState::Released, 60),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_C, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_S, KeyState::Pressed, 60),
+				ev(KeyCode::KEY_S, KeyState::Released, 60),
+				ev(KeyCode::KEY_C, KeyState::Released, 30),
+			]
+		);
+	}
+
+	#[test]
+	fn hold_on_other_key_press() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_D, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_TAB, KeyState::Pressed, 40),
+			ev(KeyCode::KEY_TAB, KeyState::Released, 60),
+			ev(KeyCode::KEY_D, KeyState::Released, 90),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_LEFTALT, KeyState::Pressed, 40),
+				ev(KeyCode::KEY_TAB, KeyState::Pressed, 40),
+				ev(KeyCode::KEY_TAB, KeyState::Released, 60),
+				ev(KeyCode::KEY_LEFTALT, KeyState::Released, 90),
+			]
+		);
+	}
+
+	#[test]
+	fn per_mapping_tapping_term() {
+		let mut engine = engine();
+		engine.process(ev(KeyCode::KEY_D, KeyState::Pressed, 0));
+		assert_eq!(engine.next_deadline(), Some(Duration::from_millis(300)));
+	}
+
+	fn tap(code: KeyCode, ms: u64) -> [KeyEvent; 2] {
+		[
+			ev(code, KeyState::Pressed, ms),
+			ev(code, KeyState::Released, ms + 1),
+		]
+	}
+
+	#[test]
+	fn momentary_layer_via_hold() {
+		let mut engine = engine();
+		let mut out = engine.process_all([
+			ev(KeyCode::KEY_SPACE, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_H, KeyState::Pressed, 250),
+		]);
+		assert_eq!(engine.active_layer(), 1);
+
+		// The layer drops before H goes up; H must still release Left.
+		out.extend(engine.process_all([
+			ev(KeyCode::KEY_SPACE, KeyState::Released, 300),
+			ev(KeyCode::KEY_H, KeyState::Released, 310),
+		]));
+		out.extend(engine.process_all(tap(KeyCode::KEY_H, 400)));
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_LEFT, KeyState::Pressed, 250),
+				ev(KeyCode::KEY_LEFT, KeyState::Released, 310),
+				ev(KeyCode::KEY_H, KeyState::Pressed, 400),
+				ev(KeyCode::KEY_H, KeyState::Released, 401),
+			]
+		);
+		assert_eq!(engine.active_layer(), 0);
+	}
+
+	#[test]
+	fn transparent_keys_fall_through() {
+		let mut engine = engine();
+		engine.process_all(tap(KeyCode::KEY_F1, 0));
+		engine.process(ev(KeyCode::KEY_SPACE, KeyState::Pressed, 10));
+		engine.tick(Duration::from_millis(500));
+
+		// nav is on top of numpad: J comes from nav, K falls through to
+		// numpad, Q is explicitly transparent and CapsLock reaches base.
+		let out = engine.process_all(
+			[
+				tap(KeyCode::KEY_J, 600),
+				tap(KeyCode::KEY_K, 610),
+				tap(KeyCode::KEY_Q, 620),
+				tap(KeyCode::KEY_CAPSLOCK, 630),
+			]
+			.concat(),
+		);
+		let pressed: Vec<KeyCode> = out
+			.iter()
+			.filter(|event| event.state == KeyState::Pressed)
+			.map(|event| event.code)
+			.collect();
+
+		assert_eq!(
+			pressed,
+			vec![
+				KeyCode::KEY_DOWN,
+				KeyCode::KEY_KP5,
+				KeyCode::KEY_Q,
+				KeyCode::KEY_ESC
+			]
+		);
+	}
+
+	#[test]
+	fn toggle_and_to_layer() {
+		let mut engine = engine();
+		engine.process_all(tap(KeyCode::KEY_F1, 0));
+		assert_eq!(engine.keymap().layer_name(engine.active_layer()), "numpad");
+		assert_eq!(
+			engine.process_all(tap(KeyCode::KEY_J, 10))[0].code,
+			KeyCode::KEY_KP4
+		);
+
+		engine.process_all(tap(KeyCode::KEY_F1, 20));
+		assert_eq!(engine.active_layer(), 0);
+
+		engine.process_all(tap(KeyCode::KEY_F1, 30));
+		engine.process_all(tap(KeyCode::KEY_F2, 40));
+		assert_eq!(engine.active_layer(), 0);
+	}
+
+	#[test]
+	fn one_shot_layer_applies_to_next_key_only() {
+		let mut engine = engine();
+		let out = engine.process_all(
+			[
+				tap(KeyCode::KEY_F2, 0),
+				tap(KeyCode::KEY_H, 10),
+				tap(KeyCode::KEY_H, 20),
+			]
+			.concat(),
+		);
+
+		assert_eq!(out[0].code, KeyCode::KEY_LEFT);
+		assert_eq!(out[1].code, KeyCode::KEY_LEFT);
+		assert_eq!(out[2].code, KeyCode::KEY_H);
+	}
+
+	#[test]
+	fn combo_fires_and_releases_on_first_key_up() {
+		let mut engine = engine();
+		let out = engine.process_all([
+			ev(KeyCode::KEY_J, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_K, KeyState::Pressed, 20),
+			ev(KeyCode::KEY_K, KeyState::Repeat, 300),
+			ev(KeyCode::KEY_K, KeyState::Released, 310),
+			ev(KeyCode::KEY_J, KeyState::Released, 320),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_ESC, KeyState::Pressed, 20),
+				ev(KeyCode::KEY_ESC, KeyState::Released, 310),
+			]
+		);
+		assert_eq!(engine.next_deadline(), None);
+	}
+
+	#[test]
+	fn lone_combo_key_passes_after_window() {
+		let mut engine = engine();
+		assert!(engine
+			.process(ev(KeyCode::KEY_J, KeyState::Pressed, 0))
+			.is_empty());
+		assert_eq!(
+			engine.tick(Duration::from_millis(60)),
+			vec![ev(KeyCode::KEY_J, KeyState::Pressed, 0)]
+		);
+
+		// K pressed long after J is an ordinary K.
+		let out = engine.process_all([
+			ev(KeyCode::KEY_K, KeyState::Pressed, 100),
+			ev(KeyCode::KEY_J, KeyState::Released, 110),
+			ev(KeyCode::KEY_K, KeyState::Released, 120),
+		]);
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_K, KeyState::Pressed, 100),
+				ev(KeyCode::KEY_J, KeyState::Released, 110),
+				ev(KeyCode::KEY_K, KeyState::Released, 120),
+			]
+		);
+	}
+
+	#[test]
+	fn combos_never_reorder_other_keys() {
+		let events = [
+			ev(KeyCode::KEY_Q, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_J, KeyState::Pressed, 10),
+			ev(KeyCode::KEY_Q, KeyState::Released, 15),
+			ev(KeyCode::KEY_J, KeyState::Released, 20),
+			ev(KeyCode::KEY_K, KeyState::Pressed, 25),
+			ev(KeyCode::KEY_W, KeyState::Pressed, 30),
+			ev(KeyCode::KEY_K, KeyState::Released, 35),
+			ev(KeyCode::KEY_W, KeyState::Released, 40),
+		];
+		assert_eq!(engine().process_all(events), events);
+	}
+
+	#[test]
+	fn longer_combo_waits_for_its_last_key() {
+		let mut engine = engine();
+		let mut out = engine.process_all([
+			ev(KeyCode::KEY_X, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_C, KeyState::Pressed, 10),
+		]);
+		assert!(out.is_empty());
+		out.extend(engine.process_all([
+			ev(KeyCode::KEY_V, KeyState::Pressed, 20),
+			ev(KeyCode::KEY_X, KeyState::Released, 30),
+			ev(KeyCode::KEY_V, KeyState::Released, 40),
+			ev(KeyCode::KEY_C, KeyState::Released, 50),
+		]));
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_F13, KeyState::Pressed, 20),
+				ev(KeyCode::KEY_F13, KeyState::Released, 30),
+			]
+		);
+
+		// Without V the shorter combo fires once the window closes.
+		let out = engine.process_all([
+			ev(KeyCode::KEY_X, KeyState::Pressed, 100),
+			ev(KeyCode::KEY_C, KeyState::Pressed, 110),
+			ev(KeyCode::KEY_C, KeyState::Released, 300),
+		]);
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_LEFTMETA, KeyState::Pressed, 110),
+				ev(KeyCode::KEY_LEFTMETA, KeyState::Released, 300),
+			]
+		);
+	}
+
+	#[test]
+	fn macro_types_text_once() {
+		let out = engine().process_all([
+			ev(KeyCode::KEY_F3, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_F3, KeyState::Repeat, 500),
+			ev(KeyCode::KEY_F3, KeyState::Released, 510),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_MINUS, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_MINUS, KeyState::Released, 0),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_DOT, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_DOT, KeyState::Released, 0),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Released, 0),
+				ev(KeyCode::KEY_SPACE, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_SPACE, KeyState::Released, 0),
+			]
+		);
+	}
+
+	#[test]
+	fn macro_delays_are_scheduled_not_waited_for() {
+		let mut engine = engine();
+		let out = engine.process_all([
+			ev(KeyCode::KEY_F4, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_F4, KeyState::Released, 10),
+			ev(KeyCode::KEY_Q, KeyState::Pressed, 20),
+			ev(KeyCode::KEY_F4, KeyState::Pressed, 30),
+		]);
+
+		assert_eq!(
+			out,
+			vec![
+				ev(KeyCode::KEY_LEFTCTRL, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_V, KeyState::Pressed, 0),
+				ev(KeyCode::KEY_V, KeyState::Released, 0),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Released, 0),
+				ev(KeyCode::KEY_LEFTCTRL, KeyState::Released, 0),
+				ev(KeyCode::KEY_ENTER, KeyState::Pressed, 100),
+				ev(KeyCode::KEY_ENTER, KeyState::Released, 100),
+				ev(KeyCode::KEY_Q, KeyState::Pressed, 20),
+				// The second run starts once the first one is done.
+				ev(KeyCode::KEY_LEFTCTRL, KeyState::Pressed, 100),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Pressed, 100),
+				ev(KeyCode::KEY_V, KeyState::Pressed, 100),
+				ev(KeyCode::KEY_V, KeyState::Released, 100),
+				ev(KeyCode::KEY_LEFTSHIFT, KeyState::Released, 100),
+				ev(KeyCode::KEY_LEFTCTRL, KeyState::Released, 100),
+				ev(KeyCode::KEY_ENTER, KeyState::Pressed, 200),
+				ev(KeyCode::KEY_ENTER, KeyState::Released, 200),
+			]
+		);
+	}
+}
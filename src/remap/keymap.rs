@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use evdev::KeyCode;
+
+use crate::config::{
+	Action, Config, MacroStep, BASE_LAYER, DEFAULT_COMBO_TERM_MS, DEFAULT_TAPPING_TERM_MS,
+};
+use crate::keys;
+
+/// Index of a layer in [`Keymap`]; the base layer is always 0.
+pub type LayerId = usize;
+
+/// Index of a macro in [`Keymap`].
+pub type MacroId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerMode {
+	/// `MO(layer)`: active while the key is held.
+	Momentary,
+	/// `TG(layer)`: each press turns the layer on or off.
+	Toggle,
+	/// `OSL(layer)`: active for the next key press only.
+	OneShot,
+	/// `TO(layer)`: the only active layer until changed again.
+	To,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerSwitch {
+	pub mode: LayerMode,
+	pub layer: LayerId,
+}
+
+/// Something a key press can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+	Key(KeyCode),
+	Layer(LayerSwitch),
+	Macro(MacroId),
+}
+
+/// What the engine does with one physical key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+	Target(Target),
+	TapHold(TapHoldBinding),
+	/// Defer to the layers below.
+	Transparent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapHoldBinding {
+	pub tap: Target,
+	pub hold: Target,
+	pub tapping_term: Duration,
+	pub permissive_hold: bool,
+	pub hold_on_other_key_press: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComboBinding {
+	pub keys: Vec<KeyCode>,
+	pub target: Target,
+	pub term: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroBinding {
+	pub name: String,
+	pub ops: Vec<MacroOp>,
+}
+
+/// A macro step with text already broken down into key taps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroOp {
+	/// Press `mods` in order, tap `key`, release `mods` in reverse.
+	Tap {
+		mods: Vec<KeyCode>,
+		key: KeyCode,
+	},
+	Delay(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layer {
+	name: String,
+	bindings: HashMap<KeyCode, Binding>,
+}
+
+/// The config's mappings and layers resolved to evdev key codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+	layers: Vec<Layer>,
+	combos: Vec<ComboBinding>,
+	macros: Vec<MacroBinding>,
+}
+
+impl Default for Keymap {
+	fn default() -> Self {
+		Self {
+			layers: vec![Layer {
+				name: BASE_LAYER.to_string(),
+				bindings: HashMap::new(),
+			}],
+			combos: Vec::new(),
+			macros: Vec::new(),
+		}
+	}
+}
+
+impl Keymap {
+	pub fn from_config(config: &Config) -> Result<Self> {
+		let default_term = config.tapping_term.unwrap_or(DEFAULT_TAPPING_TERM_MS);
+
+		if config.layers.contains_key(BASE_LAYER) {
+			bail!(
+				"layers.{}: `{}` is reserved for the [[mappings]] list",
+				BASE_LAYER,
+				BASE_LAYER
+			);
+		}
+		let names = layer_names(config);
+		let macro_names: Vec<String> = config.macros.keys().cloned().collect();
+		let target = |field: &dyn Fn() -> String, spec: &str| {
+			parse_target(spec, &names, &macro_names).with_context(field)
+		};
+
+		let mut base = HashMap::new();
+		for (idx, mapping) in config.mappings.iter().enumerate() {
+			let field = |name: &'static str| move || format!("mappings[{}].{}", idx, name);
+
+			let original = keys::parse_key(&mapping.original_key).with_context(field("original_key"))?;
+			let binding = match &mapping.action {
+				Action::Key(spec) => Binding::Target(target(&field("target_key"), spec)?),
+				Action::TapHold(tap_hold) => Binding::TapHold(TapHoldBinding {
+					tap: target(&field("tap"), &tap_hold.tap)?,
+					hold: target(&field("hold"), &tap_hold.hold)?,
+					tapping_term: Duration::from_millis(tap_hold.tapping_term.unwrap_or(default_term)),
+					permissive_hold: tap_hold.permissive_hold,
+					hold_on_other_key_press: tap_hold.hold_on_other_key_press,
+				}),
+			};
+			base.insert(original, binding);
+		}
+
+		let mut layers = vec![Layer {
+			name: BASE_LAYER.to_string(),
+			bindings: base,
+		}];
+		for (name, entries) in &config.layers {
+			let mut bindings = HashMap::new();
+			for (key, spec) in entries {
+				let field = || format!("layers.{}.{}", name, key);
+				let original = keys::parse_key(key).with_context(field)?;
+				let binding = if is_transparent(spec) {
+					Binding::Transparent
+				} else {
+					Binding::Target(target(&field, spec)?)
+				};
+				bindings.insert(original, binding);
+			}
+			layers.push(Layer {
+				name: name.clone(),
+				bindings,
+			});
+		}
+
+		let default_combo_term = config.combo_term.unwrap_or(DEFAULT_COMBO_TERM_MS);
+		let mut combos = Vec::new();
+		for (idx, combo) in config.combos.iter().enumerate() {
+			let field = |name: &'static str| move || format!("combos[{}].{}", idx, name);
+
+			let mut codes = Vec::new();
+			for key in &combo.keys {
+				let code = keys::parse_key(key).with_context(field("keys"))?;
+				if codes.contains(&code) {
+					bail!("combos[{}].keys: `{}` is listed twice", idx, key);
+				}
+				codes.push(code);
+			}
+			if codes.len() < 2 {
+				bail!("combos[{}].keys: a combo needs at least two keys", idx);
+			}
+
+			combos.push(ComboBinding {
+				keys: codes,
+				target: target(&field("target"), &combo.target)?,
+				term: Duration::from_millis(combo.timeout.unwrap_or(default_combo_term)),
+			});
+		}
+
+		let mut macros = Vec::new();
+		for (name, steps) in &config.macros {
+			let mut ops = Vec::new();
+			for (idx, step) in steps.iter().enumerate() {
+				let field = || format!("macros.{}[{}]", name, idx);
+				match step {
+					MacroStep::Keys(spec) => ops.push(parse_chord(spec).with_context(field)?),
+					MacroStep::Text { text } => {
+						for ch in text.chars() {
+							let Some((def, shifted)) = keys::from_char(ch) else {
+								bail!("{}: cannot type {:?} on a US layout", field(), ch);
+							};
+							let mods = if shifted {
+								vec![KeyCode::KEY_LEFTSHIFT]
+							} else {
+								Vec::new()
+							};
+							ops.push(MacroOp::Tap {
+								mods,
+								key: def.code,
+							});
+						}
+					}
+					MacroStep::Delay { delay } => ops.push(MacroOp::Delay(Duration::from_millis(*delay))),
+				}
+			}
+			macros.push(MacroBinding {
+				name: name.clone(),
+				ops,
+			});
+		}
+
+		Ok(Self {
+			layers,
+			combos,
+			macros,
+		})
+	}
+
+	pub fn combos(&self) -> &[ComboBinding] {
+		&self.combos
+	}
+
+	pub fn macro_binding(&self, id: MacroId) -> Option<&MacroBinding> {
+		self.macros.get(id)
+	}
+
+	/// The binding of a physical key given the active layers, topmost first.
+	/// Keys no active layer binds map to themselves.
+	pub fn binding<I>(&self, code: KeyCode, active: I) -> Binding
+	where
+		I: IntoIterator<Item = LayerId>,
+	{
+		active
+			.into_iter()
+			.chain(std::iter::once(0))
+			.filter_map(|layer| self.layers.get(layer)?.bindings.get(&code))
+			.find(|binding| **binding != Binding::Transparent)
+			.copied()
+			.unwrap_or(Binding::Target(Target::Key(code)))
+	}
+
+	/// What `layer` itself binds `code` to, ignoring the layers below.
+	pub fn layer_binding(&self, layer: LayerId, code: KeyCode) -> Option<Binding> {
+		self.layers.get(layer)?.bindings.get(&code).copied()
+	}
+
+	pub fn layer_name(&self, layer: LayerId) -> &str {
+		self
+			.layers
+			.get(layer)
+			.map_or("?", |layer| layer.name.as_str())
+	}
+
+	pub fn layer_id(&self, name: &str) -> Option<LayerId> {
+		self.layers.iter().position(|layer| layer.name == name)
+	}
+
+	pub fn layer_count(&self) -> usize {
+		self.layers.len()
+	}
+
+	/// Every key this keymap can emit.
+	pub fn targets(&self) -> impl Iterator<Item = KeyCode> + '_ {
+		self
+			.layers
+			.iter()
+			.flat_map(|layer| layer.bindings.values())
+			.flat_map(|binding| match binding {
+				Binding::Target(target) => vec![*target],
+				Binding::TapHold(tap_hold) => vec![tap_hold.tap, tap_hold.hold],
+				Binding::Transparent => Vec::new(),
+			})
+			.chain(self.combos.iter().map(|combo| combo.target))
+			.filter_map(|target| match target {
+				Target::Key(code) => Some(code),
+				Target::Layer(_) | Target::Macro(_) => None,
+			})
+			.chain(
+				self
+					.macros
+					.iter()
+					.flat_map(|binding| &binding.ops)
+					.flat_map(|op| match op {
+						MacroOp::Tap { mods, key } => {
+							mods.iter().chain(std::iter::once(key)).copied().collect()
+						}
+						MacroOp::Delay(_) => Vec::new(),
+					}),
+			)
+	}
+
+	/// Short legend for a target, as drawn on a keycap.
+	pub fn describe(&self, target: Target) -> String {
+		match target {
+			Target::Key(code) => keys::label_of(code),
+			Target::Layer(switch) => {
+				let mode = match switch.mode {
+					LayerMode::Momentary => "MO",
+					LayerMode::Toggle => "TG",
+					LayerMode::OneShot => "OSL",
+					LayerMode::To => "TO",
+				};
+				format!("{}({})", mode, self.layer_name(switch.layer))
+			}
+			Target::Macro(id) => self
+				.macros
+				.get(id)
+				.map_or_else(|| "?".to_string(), |binding| binding.name.clone()),
+		}
+	}
+
+	/// What every key `layer` binds does, keyed by the registry name of the
+	/// physical key, for the TUI keyboard to draw: its target, or the tap
+	/// then the hold target of a dual-role key.
+	pub fn legends(&self, layer: LayerId) -> HashMap<String, Vec<Target>> {
+		let Some(layer) = self.layers.get(layer) else {
+			return HashMap::new();
+		};
+		layer
+			.bindings
+			.iter()
+			.filter_map(|(code, binding)| {
+				let targets = match binding {
+					Binding::Target(target) => vec![*target],
+					Binding::TapHold(tap_hold) => vec![tap_hold.tap, tap_hold.hold],
+					Binding::Transparent => return None,
+				};
+				Some((keys::name_of(*code), targets))
+			})
+			.collect()
+	}
+}
+
+/// Whether a layer entry defers to the layers below: `_` or `transparent`.
+pub fn is_transparent(spec: &str) -> bool {
+	let spec = spec.trim();
+	spec == "_" || spec.eq_ignore_ascii_case("transparent") || spec.eq_ignore_ascii_case("trans")
+}
+
+/// Parses a target: a key name, a layer switch such as `MO(nav)` or a
+/// macro such as `MACRO(signature)`.
+pub fn parse_target(spec: &str, layers: &[String], macros: &[String]) -> Result<Target> {
+	let spec = spec.trim();
+	let call = spec.strip_suffix(')').and_then(|rest| rest.split_once('('));
+
+	let Some((function, argument)) = call else {
+		return Ok(Target::Key(keys::parse_key(spec)?));
+	};
+	let argument = argument.trim();
+
+	let mode = match function.trim().to_ascii_uppercase().as_str() {
+		"MO" => LayerMode::Momentary,
+		"TG" => LayerMode::Toggle,
+		"OSL" => LayerMode::OneShot,
+		"TO" => LayerMode::To,
+		"MACRO" => {
+			return match macros.iter().position(|name| name == argument) {
+				Some(id) => Ok(Target::Macro(id)),
+				None if macros.is_empty() => bail!("unknown macro `{}`, no macros are defined", argument),
+				None => bail!(
+					"unknown macro `{}`, defined macros are: {}",
+					argument,
+					macros.join(", ")
+				),
+			};
+		}
+		other => bail!(
+			"unknown action `{}`, expected MO, TG, OSL, TO or MACRO",
+			other
+		),
+	};
+
+	match layers.iter().position(|name| name == argument) {
+		Some(layer) => Ok(Target::Layer(LayerSwitch { mode, layer })),
+		None => bail!(
+			"unknown layer `{}`, defined layers are: {}",
+			argument,
+			layers.join(", ")
+		),
+	}
+}
+
+/// Parses a macro key step such as `Ctrl+Shift+T`.
+fn parse_chord(spec: &str) -> Result<MacroOp> {
+	let mut codes = Vec::new();
+	for part in spec.split('+') {
+		if part.trim().is_empty() {
+			bail!("empty key in `{}`, write `+` as `Shift+Equal`", spec);
+		}
+		codes.push(keys::parse_key(part)?);
+	}
+	let key = codes.pop().context("empty macro step")?;
+	Ok(MacroOp::Tap { mods: codes, key })
+}
+
+/// Layer names in [`LayerId`] order.
+pub fn layer_names(config: &Config) -> Vec<String> {
+	std::iter::once(BASE_LAYER.to_string())
+		.chain(config.layers.keys().cloned())
+		.collect()
+}
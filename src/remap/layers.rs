@@ -0,0 +1,77 @@
+use evdev::KeyCode;
+
+use super::keymap::{LayerId, LayerMode, LayerSwitch};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Active {
+	layer: LayerId,
+	mode: LayerMode,
+	/// The physical key holding a momentary layer.
+	key: KeyCode,
+}
+
+/// Layers activated on top of the base layer, in activation order. Later
+/// activations take precedence.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LayerStack {
+	active: Vec<Active>,
+}
+
+impl LayerStack {
+	/// Applies a layer switch pressed on `key`.
+	pub fn press(&mut self, switch: LayerSwitch, key: KeyCode) {
+		let entry = Active {
+			layer: switch.layer,
+			mode: switch.mode,
+			key,
+		};
+		match switch.mode {
+			LayerMode::Momentary | LayerMode::OneShot => self.active.push(entry),
+			LayerMode::Toggle => {
+				let toggled =
+					|active: &Active| active.layer == switch.layer && active.mode == LayerMode::Toggle;
+				if self.active.iter().any(toggled) {
+					self.active.retain(|active| !toggled(active));
+				} else {
+					self.active.push(entry);
+				}
+			}
+			LayerMode::To => {
+				self.active.clear();
+				if switch.layer != 0 {
+					self.active.push(entry);
+				}
+			}
+		}
+	}
+
+	/// Undoes a momentary switch when the key holding it goes up.
+	pub fn release(&mut self, switch: LayerSwitch, key: KeyCode) {
+		if switch.mode == LayerMode::Momentary {
+			self
+				.active
+				.retain(|active| !(active.mode == LayerMode::Momentary && active.key == key));
+		}
+	}
+
+	/// Drops one-shot layers once the key they were waiting for went down.
+	pub fn consume_one_shot(&mut self) {
+		self
+			.active
+			.retain(|active| active.mode != LayerMode::OneShot);
+	}
+
+	/// Active layers, topmost first, not including the base layer.
+	pub fn iter(&self) -> impl Iterator<Item = LayerId> + '_ {
+		self.active.iter().rev().map(|active| active.layer)
+	}
+
+	/// The topmost active layer, the base layer when nothing is active.
+	pub fn top(&self) -> LayerId {
+		self.iter().next().unwrap_or(0)
+	}
+
+	pub fn clear(&mut self) {
+		self.active.clear();
+	}
+}
@@ -0,0 +1,20 @@
+mod combos;
+mod daemon;
+mod engine;
+mod keymap;
+mod layers;
+pub mod monitor;
+mod output;
+mod panic;
+pub mod reload;
+pub mod status;
+mod tap_hold;
+
+pub use daemon::run;
+pub use engine::{Engine, KeyEvent, KeyState, Undecided};
+pub use keymap::{
+	is_transparent, layer_names, parse_target, Binding, ComboBinding, Keymap, LayerId, LayerMode,
+	LayerSwitch, TapHoldBinding, Target,
+};
+pub use output::{Schedule, VirtualKeyboard, VIRTUAL_KEYBOARD_NAME};
+pub use panic::PanicWatch;
@@ -0,0 +1,164 @@
+//! Shows what the engine makes of a device's events without grabbing it
+//! or typing anything: each physical key event beside what was emitted
+//! for it, with the active layer and the decisions still open.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use super::daemon::{find_device, now, open, wait_readable};
+use super::{Engine, KeyEvent, Keymap, Undecided};
+use crate::config::Config;
+use crate::keys;
+
+/// Prints a line per key event of the device until interrupted.
+pub fn run(config: &Config, device_path: Option<&Path>) -> Result<()> {
+	let mut engine = Engine::new(Keymap::from_config(config)?);
+	let info = find_device(config, device_path)?;
+	let mut device = open(&info.path)?;
+	eprintln!(
+		"Monitoring {} ({}), press Ctrl+C to stop",
+		info.name,
+		info.path.display()
+	);
+
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	loop {
+		let timeout = engine
+			.next_deadline()
+			.map(|deadline| deadline.saturating_sub(now()));
+		if wait_readable(&device, &[], timeout)? {
+			for event in device
+				.fetch_events()
+				.with_context(|| format!("Cannot read from {}", info.path.display()))?
+			{
+				if let Some(event) = KeyEvent::from_input(&event) {
+					let emitted = engine.process(event);
+					writeln!(out, "{}", line(Some(&event), &emitted, &engine))?;
+				}
+			}
+		}
+		// Decisions the clock settled, with nothing pressed
+		let emitted = engine.tick(now());
+		if !emitted.is_empty() {
+			writeln!(out, "{}", line(None, &emitted, &engine))?;
+		}
+	}
+}
+
+/// One line of the monitor: the physical event, or `timeout` when the
+/// clock made the engine emit, then what it emitted, the active layer and
+/// what is still undecided.
+pub fn line(physical: Option<&KeyEvent>, emitted: &[KeyEvent], engine: &Engine) -> String {
+	let (time, input) = match physical {
+		Some(event) => (
+			event.time,
+			format!(
+				"{:>3} {:<12} {}",
+				event.code.code(),
+				keys::name_of(event.code),
+				event.state.name()
+			),
+		),
+		None => (
+			emitted.first().map_or(Duration::ZERO, |event| event.time),
+			"timeout".to_string(),
+		),
+	};
+
+	let undecided = engine.undecided();
+	let output = if !emitted.is_empty() {
+		emitted
+			.iter()
+			.map(|event| format!("{} {}", keys::name_of(event.code), event.state.name()))
+			.collect::<Vec<_>>()
+			.join(", ")
+	} else if undecided.is_empty() {
+		"nothing".to_string()
+	} else {
+		"held back".to_string()
+	};
+
+	let mut line = format!(
+		"{:>17.6}  {:<23} → {:<28} [{}]",
+		time.as_secs_f64(),
+		input,
+		output,
+		engine.keymap().layer_name(engine.active_layer())
+	);
+	for decision in undecided {
+		let waiting = match decision {
+			Undecided::TapHold {
+				key,
+				deadline,
+				buffered,
+			} => format!(
+				"{} tap or hold, hold in {:.3}s, {} buffered",
+				keys::name_of(key),
+				deadline.saturating_sub(time).as_secs_f64(),
+				buffered
+			),
+			Undecided::Combo { keys, deadline } => format!(
+				"combo of {}, settled in {:.3}s",
+				keys
+					.iter()
+					.map(|code| keys::name_of(*code))
+					.collect::<Vec<_>>()
+					.join("+"),
+				deadline.saturating_sub(time).as_secs_f64()
+			),
+		};
+		line.push_str("  · ");
+		line.push_str(&waiting);
+	}
+	line
+}
+
+#[cfg(test)]
+mod tests {
+	use evdev::KeyCode;
+
+	use super::*;
+	use crate::remap::KeyState;
+
+	#[test]
+	fn shows_pending_decisions() {
+		let config: Config = r#"
+name = "x"
+
+[[mappings]]
+original_key = "caps"
+tap = "Esc"
+hold = "LCtrl"
+tapping_term = 200
+
+[[combos]]
+keys = ["J", "K"]
+target = "Escape"
+"#
+		.parse()
+		.unwrap();
+		let mut engine = Engine::new(Keymap::from_config(&config).unwrap());
+		let mut feed = |code, state, ms| {
+			let event = KeyEvent::new(code, state, Duration::from_millis(ms));
+			let emitted = engine.process(event);
+			line(Some(&event), &emitted, &engine)
+		};
+
+		assert_eq!(
+			feed(KeyCode::KEY_CAPSLOCK, KeyState::Pressed, 1000),
+			"         1.000000   58 CapsLock     down   → held back                    [base]  · CapsLock tap or hold, hold in 0.200s, 0 buffered"
+		);
+		assert_eq!(
+			feed(KeyCode::KEY_J, KeyState::Pressed, 1050),
+			"         1.050000   36 J            down   → held back                    [base]  · combo of J, settled in 0.050s  · CapsLock tap or hold, hold in 0.150s, 0 buffered"
+		);
+		assert_eq!(
+			feed(KeyCode::KEY_CAPSLOCK, KeyState::Released, 1100),
+			"         1.100000   58 CapsLock     up     → Escape down, Escape up, J down [base]"
+		);
+	}
+}
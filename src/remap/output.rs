@@ -0,0 +1,214 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, Device, EventType, InputEvent, KeyCode, UinputAbsSetup};
+
+use super::{KeyEvent, KeyState};
+use crate::keys;
+
+pub const VIRTUAL_KEYBOARD_NAME: &str = "geekCaps virtual keyboard";
+
+/// The uinput keyboard remapped events are written to.
+pub struct VirtualKeyboard {
+	device: VirtualDevice,
+	schedule: Schedule,
+	/// Keys the device was created with; no others can be typed.
+	supported: AttributeSet<KeyCode>,
+	/// Keys currently down on the virtual device.
+	down: HashSet<KeyCode>,
+}
+
+impl VirtualKeyboard {
+	/// Creates the virtual device. It advertises every key in the registry
+	/// plus `extra` so anything the engine emits is accepted by the kernel,
+	/// and whatever else `source` reports (pointer motion, scancodes,
+	/// switches) so its other events can be [forwarded](Self::forward).
+	pub fn create<I>(source: Option<&Device>, extra: I) -> Result<Self>
+	where
+		I: IntoIterator<Item = KeyCode>,
+	{
+		let mut supported = AttributeSet::<KeyCode>::new();
+		for def in keys::KEYS {
+			supported.insert(def.code);
+		}
+		let source_keys = source
+			.and_then(Device::supported_keys)
+			.into_iter()
+			.flat_map(|keys| keys.iter());
+		for code in source_keys.chain(extra) {
+			supported.insert(code);
+		}
+
+		let mut builder = VirtualDevice::builder()
+			.context("Cannot open /dev/uinput (is the uinput module loaded and writable?)")?
+			.name(VIRTUAL_KEYBOARD_NAME)
+			.with_keys(&supported)?;
+		if let Some(source) = source {
+			builder = builder.with_properties(source.properties())?;
+			if let Some(axes) = source.supported_relative_axes() {
+				builder = builder.with_relative_axes(axes)?;
+			}
+			for (axis, info) in source
+				.get_absinfo()
+				.context("Cannot read the absolute axes of the keyboard")?
+			{
+				builder = builder.with_absolute_axis(&UinputAbsSetup::new(axis, info))?;
+			}
+			if let Some(misc) = source.misc_properties() {
+				builder = builder.with_msc(misc)?;
+			}
+			if let Some(switches) = source.supported_switches() {
+				builder = builder.with_switches(switches)?;
+			}
+		}
+		let device = builder
+			.build()
+			.context("Cannot create the virtual keyboard")?;
+
+		Ok(Self {
+			device,
+			schedule: Schedule::default(),
+			supported,
+			down: HashSet::new(),
+		})
+	}
+
+	pub fn supports(&self, code: KeyCode) -> bool {
+		self.supported.contains(code)
+	}
+
+	/// Writes the events due by `now` and holds back the rest (macro steps
+	/// after a delay) until a later call. Events that are due never wait
+	/// behind scheduled ones.
+	pub fn emit(&mut self, events: &[KeyEvent], now: Duration) -> Result<()> {
+		self.emit_scanned(events, &[], now)
+	}
+
+	/// Like [`emit`](Self::emit), for the `events` a key became. `scan`,
+	/// the scancode the keyboard sent with that key, goes in the report of
+	/// the first of them written now, and is dropped when none is.
+	pub fn emit_scanned(
+		&mut self,
+		events: &[KeyEvent],
+		scan: &[InputEvent],
+		now: Duration,
+	) -> Result<()> {
+		let scheduled = self.schedule.due(now);
+		let mut due = Vec::new();
+		for event in events {
+			if event.time <= now {
+				due.push(*event);
+			} else {
+				self.schedule.push(*event);
+			}
+		}
+
+		// One report per event: a press and release of the same key in a
+		// single report is dropped by some clients.
+		self.write(&scheduled, &[])?;
+		self.write(&due, scan)
+	}
+
+	/// Drops held back events and releases every key still down.
+	pub fn release_all(&mut self, time: Duration) -> Result<()> {
+		self.schedule = Schedule::default();
+		let released: Vec<KeyEvent> = self
+			.down
+			.iter()
+			.map(|code| KeyEvent::new(*code, KeyState::Released, time))
+			.collect();
+		self.write(&released, &[])
+	}
+
+	/// Writes each event in a report of its own, the first with `scan`.
+	fn write(&mut self, events: &[KeyEvent], mut scan: &[InputEvent]) -> Result<()> {
+		for event in events {
+			match event.state {
+				KeyState::Pressed => self.down.insert(event.code),
+				KeyState::Released => self.down.remove(&event.code),
+				KeyState::Repeat => false,
+			};
+			let report: Vec<InputEvent> = scan.iter().copied().chain([event.to_input()]).collect();
+			self
+				.device
+				.emit(&report)
+				.context("Cannot write to the virtual keyboard")?;
+			scan = &[];
+		}
+		Ok(())
+	}
+
+	/// Writes events that are not remapped, unchanged and in the reports
+	/// the device grouped them in.
+	pub fn forward(&mut self, events: &[InputEvent]) -> Result<()> {
+		for report in events.split(|event| event.event_type() == EventType::SYNCHRONIZATION) {
+			if !report.is_empty() {
+				self
+					.device
+					.emit(report)
+					.context("Cannot write to the virtual keyboard")?;
+			}
+		}
+		Ok(())
+	}
+
+	/// When the next held back event is due, if any.
+	pub fn next_deadline(&self) -> Option<Duration> {
+		self.schedule.next_deadline()
+	}
+}
+
+/// Events waiting for their timestamp, in the order they become due.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Schedule {
+	queue: VecDeque<KeyEvent>,
+}
+
+impl Schedule {
+	pub fn push(&mut self, event: KeyEvent) {
+		let at = self
+			.queue
+			.partition_point(|queued| queued.time <= event.time);
+		self.queue.insert(at, event);
+	}
+
+	pub fn due(&mut self, now: Duration) -> Vec<KeyEvent> {
+		let count = self.queue.partition_point(|queued| queued.time <= now);
+		self.queue.drain(..count).collect()
+	}
+
+	pub fn next_deadline(&self) -> Option<Duration> {
+		self.queue.front().map(|event| event.time)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ev(code: KeyCode, ms: u64) -> KeyEvent {
+		KeyEvent::new(code, KeyState::Pressed, Duration::from_millis(ms))
+	}
+
+	#[test]
+	fn schedule_releases_events_in_time_order() {
+		let mut schedule = Schedule::default();
+		schedule.push(ev(KeyCode::KEY_B, 200));
+		schedule.push(ev(KeyCode::KEY_A, 100));
+		schedule.push(ev(KeyCode::KEY_C, 200));
+
+		assert_eq!(schedule.next_deadline(), Some(Duration::from_millis(100)));
+		assert!(schedule.due(Duration::from_millis(50)).is_empty());
+		assert_eq!(
+			schedule.due(Duration::from_millis(250)),
+			vec![
+				ev(KeyCode::KEY_A, 100),
+				ev(KeyCode::KEY_B, 200),
+				ev(KeyCode::KEY_C, 200)
+			]
+		);
+		assert_eq!(schedule.next_deadline(), None);
+	}
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use evdev::KeyCode;
+
+use super::{KeyEvent, KeyState};
+use crate::config::Config;
+use crate::keys;
+
+/// Watches the physical keys for the panic chord. It sees events before the
+/// engine does, so no mapping can swallow the chord.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicWatch {
+	keys: Vec<KeyCode>,
+	hold: Duration,
+	/// When each chord key currently down went down.
+	down: HashMap<KeyCode, Duration>,
+}
+
+impl PanicWatch {
+	pub fn new(keys: Vec<KeyCode>, hold: Duration) -> Self {
+		Self {
+			keys,
+			hold,
+			down: HashMap::new(),
+		}
+	}
+
+	pub fn from_config(config: &Config) -> Result<Self> {
+		let chord = config.panic.clone().unwrap_or_default();
+		if chord.keys.is_empty() {
+			bail!("panic.keys: the panic chord needs at least one key");
+		}
+		let keys = chord
+			.keys
+			.iter()
+			.map(|key| keys::parse_key(key))
+			.collect::<Result<Vec<_>, _>>()
+			.context("panic.keys")?;
+		Ok(Self::new(keys, Duration::from_millis(chord.hold)))
+	}
+
+	pub fn feed(&mut self, event: &KeyEvent) {
+		if !self.keys.contains(&event.code) {
+			return;
+		}
+		match event.state {
+			KeyState::Pressed => {
+				self.down.entry(event.code).or_insert(event.time);
+			}
+			KeyState::Repeat => {}
+			KeyState::Released => {
+				self.down.remove(&event.code);
+			}
+		}
+	}
+
+	/// When the chord fires if every key stays down, `None` unless they all
+	/// are down already.
+	pub fn deadline(&self) -> Option<Duration> {
+		if self.down.len() < self.keys.len() {
+			return None;
+		}
+		self.down.values().max().map(|pressed| *pressed + self.hold)
+	}
+
+	pub fn fired(&self, now: Duration) -> bool {
+		self.deadline().is_some_and(|deadline| deadline <= now)
+	}
+
+	/// The chord as written in the config, e.g. `LeftShift+RightShift+Escape`.
+	pub fn describe(&self) -> String {
+		self
+			.keys
+			.iter()
+			.map(|code| keys::name_of(*code))
+			.collect::<Vec<_>>()
+			.join("+")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::remap::{Engine, Keymap};
+
+	fn ev(code: KeyCode, state: KeyState, ms: u64) -> KeyEvent {
+		KeyEvent::new(code, state, Duration::from_millis(ms))
+	}
+
+	/// Every chord key is remapped to something else.
+	const CONFIG: &str = r#"
+name = "panic"
+
+[[mappings]]
+original_key = "Escape"
+target_key = "CapsLock"
+
+[[mappings]]
+original_key = "LeftShift"
+tap = "F13"
+hold = "MO(trap)"
+
+[[mappings]]
+original_key = "RightShift"
+target_key = "MACRO(nothing)"
+
+[macros]
+nothing = [{ delay = 10 }]
+
+[layers.trap]
+Escape = "F14"
+"#;
+
+	/// Feeds events the way the daemon does: the watch sees every physical
+	/// event before the engine.
+	fn run(events: &[KeyEvent]) -> (PanicWatch, Vec<KeyEvent>) {
+		let config: Config = CONFIG.parse().unwrap();
+		let mut watch = PanicWatch::from_config(&config).unwrap();
+		let mut engine = Engine::new(Keymap::from_config(&config).unwrap());
+		let mut out = Vec::new();
+		for event in events {
+			watch.feed(event);
+			out.extend(engine.process(*event));
+		}
+		(watch, out)
+	}
+
+	#[test]
+	fn fires_on_remapped_keys() {
+		let (watch, out) = run(&[
+			ev(KeyCode::KEY_LEFTSHIFT, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_RIGHTSHIFT, KeyState::Pressed, 100),
+			ev(KeyCode::KEY_ESC, KeyState::Pressed, 300),
+			ev(KeyCode::KEY_ESC, KeyState::Repeat, 800),
+		]);
+
+		// The engine never lets any of the chord keys through as themselves.
+		assert!(out.iter().all(|event| !matches!(
+			event.code,
+			KeyCode::KEY_LEFTSHIFT | KeyCode::KEY_RIGHTSHIFT | KeyCode::KEY_ESC
+		)));
+
+		assert_eq!(watch.deadline(), Some(Duration::from_millis(2300)));
+		assert!(!watch.fired(Duration::from_millis(2299)));
+		assert!(watch.fired(Duration::from_millis(2300)));
+	}
+
+	#[test]
+	fn releasing_a_key_cancels() {
+		let (watch, _) = run(&[
+			ev(KeyCode::KEY_LEFTSHIFT, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_RIGHTSHIFT, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_ESC, KeyState::Pressed, 0),
+			ev(KeyCode::KEY_RIGHTSHIFT, KeyState::Released, 1500),
+		]);
+		assert_eq!(watch.deadline(), None);
+		assert!(!watch.fired(Duration::from_secs(10)));
+	}
+
+	#[test]
+	fn configurable_chord() {
+		let config: Config = "name = \"x\"\n[panic]\nkeys = [\"LeftCtrl\", \"F12\"]\nhold = 500\n"
+			.parse()
+			.unwrap();
+		let mut watch = PanicWatch::from_config(&config).unwrap();
+		assert_eq!(watch.describe(), "LeftCtrl+F12");
+
+		watch.feed(&ev(KeyCode::KEY_LEFTCTRL, KeyState::Pressed, 0));
+		watch.feed(&ev(KeyCode::KEY_F12, KeyState::Pressed, 100));
+		assert_eq!(watch.deadline(), Some(Duration::from_millis(600)));
+	}
+}
@@ -0,0 +1,137 @@
+//! Follows the config file so the daemon takes edits without letting go
+//! of the keyboard. The directory is watched rather than the file, as
+//! editors often save by renaming a new file over the old one.
+
+use std::ffi::OsString;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use nix::errno::Errno;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use super::{Keymap, PanicWatch};
+use crate::config::lint::{self, Severity};
+use crate::config::Config;
+
+pub struct ConfigWatch {
+	inotify: Inotify,
+	path: PathBuf,
+	name: OsString,
+}
+
+impl ConfigWatch {
+	pub fn new(path: &Path) -> Result<Self> {
+		let name = path
+			.file_name()
+			.with_context(|| format!("{} is not a file", path.display()))?
+			.to_os_string();
+		let dir = match path.parent() {
+			Some(dir) if !dir.as_os_str().is_empty() => dir,
+			_ => Path::new("."),
+		};
+		let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+			.context("Cannot watch files")?;
+		inotify
+			.add_watch(
+				dir,
+				AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO,
+			)
+			.with_context(|| format!("Cannot watch {}", dir.display()))?;
+		Ok(Self {
+			inotify,
+			path: path.to_path_buf(),
+			name,
+		})
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Whether the file was written or replaced since last asked. Never
+	/// blocks.
+	pub fn changed(&self) -> Result<bool> {
+		let mut changed = false;
+		loop {
+			match self.inotify.read_events() {
+				Ok(events) => {
+					changed |= events
+						.iter()
+						.any(|event| event.name.as_ref() == Some(&self.name));
+				}
+				Err(Errno::EAGAIN) => return Ok(changed),
+				Err(Errno::EINTR) => {}
+				Err(err) => {
+					return Err(err).with_context(|| format!("Cannot watch {}", self.path.display()))
+				}
+			}
+		}
+	}
+}
+
+impl AsFd for ConfigWatch {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.inotify.as_fd()
+	}
+}
+
+/// Reads the config for the daemon, on starting and again on every save,
+/// refusing it if `validate` finds errors in it.
+pub fn load(path: &Path) -> Result<(Config, Keymap, PanicWatch)> {
+	let config = Config::load(path)?;
+	let errors: Vec<String> = lint::lint(&config)
+		.into_iter()
+		.filter(|diagnostic| diagnostic.severity == Severity::Error)
+		.map(|diagnostic| diagnostic.to_string())
+		.collect();
+	if !errors.is_empty() {
+		bail!("Invalid config {}:\n{}", path.display(), errors.join("\n"));
+	}
+	let keymap = Keymap::from_config(&config)?;
+	let panic = PanicWatch::from_config(&config)?;
+	Ok((config, keymap, panic))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::*;
+
+	#[test]
+	fn notices_writes_and_renames() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let path = dir.join("config.toml");
+		fs::write(&path, "name = \"x\"\n").unwrap();
+
+		let watch = ConfigWatch::new(&path).unwrap();
+		assert!(!watch.changed().unwrap());
+		fs::write(&path, "name = \"y\"\n").unwrap();
+		assert!(watch.changed().unwrap());
+		assert!(!watch.changed().unwrap());
+
+		fs::write(dir.join("other.toml"), "").unwrap();
+		assert!(!watch.changed().unwrap());
+		fs::write(dir.join("config.toml.new"), "name = \"z\"\n").unwrap();
+		let _ = watch.changed();
+		fs::rename(dir.join("config.toml.new"), &path).unwrap();
+		assert!(watch.changed().unwrap());
+
+		let (config, _, _) = load(&path).unwrap();
+		assert_eq!(config.name, "z");
+		fs::write(&path, "name = \"x\"\n[panic]\nkeys = []\n").unwrap();
+		assert!(load(&path).unwrap_err().to_string().contains("panic.keys"));
+		// The keymap alone would take it
+		fs::write(
+			&path,
+			"name = \"x\"\n[[mappings]]\noriginal_key = \"A\"\ntarget_key = \"B\"\n[[mappings]]\noriginal_key = \"A\"\ntarget_key = \"C\"\n",
+		)
+		.unwrap();
+		assert!(load(&path)
+			.unwrap_err()
+			.to_string()
+			.contains("duplicate-mapping"));
+	}
+}
@@ -0,0 +1,193 @@
+//! Tells clients such as the keyboard view which layer the daemon has
+//! active: one `layer <name>` line on connecting and after every change,
+//! over a Unix socket. Clients only listen; nothing they send is read.
+
+use std::env;
+use std::fs::{self, DirBuilder, Permissions};
+use std::io::Write;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use nix::unistd::geteuid;
+
+/// Where a daemon run as root listens. Only root may create files there,
+/// so no other user can take the name first.
+const SYSTEM_DIR: &str = "/run/geekcaps";
+
+const SOCKET_NAME: &str = "geekcaps.sock";
+
+/// Where this user's daemon listens: [`SYSTEM_DIR`] for root, the runtime
+/// directory otherwise. `None` when a user has no runtime directory.
+pub fn socket_path() -> Option<PathBuf> {
+	if geteuid().is_root() {
+		Some(Path::new(SYSTEM_DIR).join(SOCKET_NAME))
+	} else {
+		runtime_dir().map(|dir| dir.join(SOCKET_NAME))
+	}
+}
+
+/// Where a client looks for a daemon: one run by this user first, then
+/// one run by root, which is the usual way.
+pub fn socket_paths() -> Vec<PathBuf> {
+	runtime_dir()
+		.map(|dir| dir.join(SOCKET_NAME))
+		.into_iter()
+		.chain([Path::new(SYSTEM_DIR).join(SOCKET_NAME)])
+		.collect()
+}
+
+fn runtime_dir() -> Option<PathBuf> {
+	env::var_os("XDG_RUNTIME_DIR")
+		.filter(|dir| !dir.is_empty())
+		.map(PathBuf::from)
+}
+
+/// Whether the socket at `path` was made by this user or root, rather than
+/// by someone posing as the daemon.
+pub fn is_trusted(path: &Path) -> bool {
+	let uid = geteuid().as_raw();
+	fs::symlink_metadata(path).is_ok_and(|metadata| {
+		metadata.file_type().is_socket() && (metadata.uid() == 0 || metadata.uid() == uid)
+	})
+}
+
+/// Creates `dir` if needed and checks no other user can put files in it.
+fn private_dir(dir: &Path) -> Result<()> {
+	DirBuilder::new()
+		.recursive(true)
+		.mode(0o755)
+		.create(dir)
+		.with_context(|| format!("Cannot create directory {}", dir.display()))?;
+	let metadata =
+		fs::metadata(dir).with_context(|| format!("Cannot read directory {}", dir.display()))?;
+	if metadata.uid() != geteuid().as_raw() || metadata.mode() & 0o022 != 0 {
+		bail!("{} is writable by other users", dir.display());
+	}
+	Ok(())
+}
+
+/// The `layer <name>` line announcing `layer`.
+pub fn layer_line(layer: &str) -> String {
+	format!("layer {}\n", layer)
+}
+
+/// The layer a status line announces, `None` for other lines.
+pub fn parse_line(line: &str) -> Option<&str> {
+	line.strip_prefix("layer ").map(str::trim_end)
+}
+
+pub struct StatusServer {
+	path: PathBuf,
+	listener: UnixListener,
+	clients: Vec<UnixStream>,
+	/// The layer last published, sent to clients as they connect.
+	layer: String,
+}
+
+impl StatusServer {
+	/// Listens on `path`, replacing a socket left behind by a daemon that
+	/// did not exit cleanly. A live one is left alone. The directory must
+	/// belong to this user alone.
+	pub fn bind(path: &Path) -> Result<Self> {
+		if let Some(dir) = path.parent() {
+			private_dir(dir)?;
+		}
+		if UnixStream::connect(path).is_ok() {
+			bail!("{} is in use by another daemon", path.display());
+		}
+		let _ = fs::remove_file(path);
+		let listener =
+			UnixListener::bind(path).with_context(|| format!("Cannot listen on {}", path.display()))?;
+		// Anyone may watch the layers, as the viewer seldom runs as root
+		fs::set_permissions(path, Permissions::from_mode(0o666))
+			.with_context(|| format!("Cannot share {}", path.display()))?;
+		listener
+			.set_nonblocking(true)
+			.with_context(|| format!("Cannot listen on {} without blocking", path.display()))?;
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			listener,
+			clients: Vec::new(),
+			layer: String::new(),
+		})
+	}
+
+	/// Takes clients waiting to connect and tells them the active layer.
+	pub fn accept(&mut self) {
+		while let Ok((mut client, _)) = self.listener.accept() {
+			let sent = client.set_nonblocking(true).is_ok()
+				&& (self.layer.is_empty() || client.write_all(layer_line(&self.layer).as_bytes()).is_ok());
+			if sent {
+				self.clients.push(client);
+			}
+		}
+	}
+
+	/// Announces `layer` if it changed. Clients that cannot keep up are
+	/// dropped rather than waited for.
+	pub fn publish(&mut self, layer: &str) {
+		if layer == self.layer {
+			return;
+		}
+		self.layer = layer.to_string();
+		let line = layer_line(layer);
+		self
+			.clients
+			.retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+	}
+}
+
+impl AsFd for StatusServer {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.listener.as_fd()
+	}
+}
+
+impl Drop for StatusServer {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{BufRead, BufReader};
+
+	use super::*;
+
+	#[test]
+	fn announces_layer_changes() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("run").join(SOCKET_NAME);
+		let mut server = StatusServer::bind(&path).unwrap();
+		assert!(is_trusted(&path));
+		server.publish("base");
+
+		let client = UnixStream::connect(&path).unwrap();
+		server.accept();
+		server.publish("nav");
+		server.publish("nav");
+		drop(server);
+		assert!(!path.exists());
+
+		let lines: Vec<String> = BufReader::new(client).lines().map(Result::unwrap).collect();
+		assert_eq!(lines, ["layer base", "layer nav"]);
+		assert_eq!(parse_line(&lines[1]), Some("nav"));
+	}
+
+	#[test]
+	fn refuses_shared_directories() {
+		let dir = tempfile::tempdir().unwrap();
+		fs::set_permissions(dir.path(), Permissions::from_mode(0o777)).unwrap();
+		let path = dir.path().join(SOCKET_NAME);
+		let err = StatusServer::bind(&path).err().unwrap();
+		assert!(err.to_string().ends_with("is writable by other users"));
+
+		fs::write(&path, "").unwrap();
+		assert!(!is_trusted(&path));
+	}
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use evdev::KeyCode;
+
+use super::combos::Step;
+use super::keymap::TapHoldBinding;
+use super::KeyState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Decision {
+	Undecided,
+	Tap,
+	Hold,
+}
+
+/// A dual-role key that is down but whose role is not known yet. Events
+/// arriving meanwhile are buffered and replayed in order once it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PendingTapHold {
+	pub key: KeyCode,
+	pub binding: TapHoldBinding,
+	pub pressed_at: Duration,
+	pub buffered: Vec<Step>,
+}
+
+impl PendingTapHold {
+	pub fn new(key: KeyCode, binding: TapHoldBinding, pressed_at: Duration) -> Self {
+		Self {
+			key,
+			binding,
+			pressed_at,
+			buffered: Vec::new(),
+		}
+	}
+
+	/// When the key becomes a hold if nothing else decides it first.
+	pub fn deadline(&self) -> Duration {
+		self.pressed_at + self.binding.tapping_term
+	}
+
+	/// Feeds an event that arrived before the deadline.
+	pub fn feed(&mut self, step: Step) -> Decision {
+		if let Step::Key(event) = step {
+			if event.code == self.key {
+				// Auto-repeat of the undecided key itself is dropped.
+				return match event.state {
+					KeyState::Released => Decision::Tap,
+					_ => Decision::Undecided,
+				};
+			}
+		}
+
+		self.buffered.push(step);
+		match step.state() {
+			KeyState::Pressed if self.binding.hold_on_other_key_press => Decision::Hold,
+			KeyState::Released if self.binding.permissive_hold && self.pressed_since(step.code()) => {
+				Decision::Hold
+			}
+			_ => Decision::Undecided,
+		}
+	}
+
+	/// Whether `code` went down after this key did, i.e. was tapped inside it.
+	fn pressed_since(&self, code: KeyCode) -> bool {
+		self
+			.buffered
+			.iter()
+			.any(|step| step.code() == code && step.state() == KeyState::Pressed)
+	}
+}
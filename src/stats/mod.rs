@@ -0,0 +1,323 @@
+//! Key press counts kept across sessions, drawn by the keyboard view's
+//! heatmap and printed by `stats`. Only totals are stored, never the order
+//! keys came in beyond pairs of keys, and those only when asked for.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+	/// Presses per registry key name.
+	pub keys: BTreeMap<String, u64>,
+	/// Presses per layer active when the key went down.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub layers: BTreeMap<String, u64>,
+	/// Presses per hour of the day, in UTC.
+	#[serde(skip_serializing_if = "is_zero")]
+	pub hours: [u64; 24],
+	/// Keys pressed one after the other, as `"T H"`.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub bigrams: BTreeMap<String, u64>,
+}
+
+fn is_zero(hours: &[u64; 24]) -> bool {
+	hours.iter().all(|&count| count == 0)
+}
+
+fn add_counts(to: &mut BTreeMap<String, u64>, from: &BTreeMap<String, u64>) {
+	for (key, count) in from {
+		*to.entry(key.clone()).or_default() += count;
+	}
+}
+
+/// The `n` highest counts, ties by name.
+pub fn top(counts: &BTreeMap<String, u64>, n: usize) -> Vec<(&str, u64)> {
+	let mut counts: Vec<(&str, u64)> = counts
+		.iter()
+		.map(|(key, count)| (key.as_str(), *count))
+		.collect();
+	counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+	counts.truncate(n);
+	counts
+}
+
+impl Stats {
+	/// `$XDG_DATA_HOME/geekcaps/stats.json`, under `~/.local/share` when
+	/// the variable is unset. `None` without a home directory.
+	pub fn default_path() -> Option<PathBuf> {
+		let data = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+			Some(dir) => PathBuf::from(dir),
+			None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+		};
+		Some(data.join("geekcaps").join("stats.json"))
+	}
+
+	/// Reads a stats file. A missing file holds no presses yet.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		let path = path.as_ref();
+		let source = match fs::read_to_string(path) {
+			Ok(source) => source,
+			Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+			Err(err) => {
+				return Err(err).with_context(|| format!("Cannot read stats file {}", path.display()))
+			}
+		};
+		serde_json::from_str(&source).with_context(|| format!("Invalid stats file {}", path.display()))
+	}
+
+	/// Counts a press of the key with this registry name.
+	pub fn record(&mut self, key: &str) {
+		*self.keys.entry(key.to_string()).or_default() += 1;
+	}
+
+	pub fn merge(&mut self, other: &Stats) {
+		add_counts(&mut self.keys, &other.keys);
+		add_counts(&mut self.layers, &other.layers);
+		add_counts(&mut self.bigrams, &other.bigrams);
+		for (hour, count) in self.hours.iter_mut().zip(other.hours) {
+			*hour += count;
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.keys.is_empty()
+	}
+
+	pub fn total(&self) -> u64 {
+		self.keys.values().sum()
+	}
+}
+
+/// How long a one-off change waits for another process adding to the file.
+pub const LOCK_PATIENCE: Duration = Duration::from_secs(1);
+
+/// A stats file locked for writing through `<file>.lock`. Only the holder
+/// changes the file, so no counts are lost to another writer. `start`
+/// holds it for as long as it counts, so the same presses are never added
+/// twice by a keyboard view watching the same device.
+pub struct StatsLock {
+	path: PathBuf,
+	_lock: Flock<File>,
+}
+
+impl StatsLock {
+	/// Locks the stats file at `path`, waiting up to `patience` for another
+	/// process to let go. `None` if it still holds the lock.
+	pub fn acquire(path: &Path, patience: Duration) -> Result<Option<Self>> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)
+				.with_context(|| format!("Cannot create directory {}", dir.display()))?;
+		}
+		let mut lock_path = path.as_os_str().to_owned();
+		lock_path.push(".lock");
+		let lock_path = PathBuf::from(lock_path);
+		let mut file = OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.write(true)
+			.open(&lock_path)
+			.with_context(|| format!("Cannot open lock file {}", lock_path.display()))?;
+
+		let started = Instant::now();
+		loop {
+			match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+				Ok(lock) => {
+					return Ok(Some(Self {
+						path: path.to_path_buf(),
+						_lock: lock,
+					}))
+				}
+				Err((_, Errno::EWOULDBLOCK)) if started.elapsed() >= patience => return Ok(None),
+				Err((unlocked, Errno::EWOULDBLOCK | Errno::EINTR)) => {
+					file = unlocked;
+					thread::sleep(Duration::from_millis(10));
+				}
+				Err((_, err)) => {
+					return Err(err).with_context(|| format!("Cannot lock {}", lock_path.display()))
+				}
+			}
+		}
+	}
+
+	/// Locks the stats file at `path` for a one-off change, failing while
+	/// `start` counts into it.
+	pub fn wait(path: &Path) -> Result<Self> {
+		Self::acquire(path, LOCK_PATIENCE)?.with_context(|| {
+			format!(
+				"{} is in use, most likely by `start` counting presses",
+				path.display()
+			)
+		})
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Adds `stats` to the file rather than overwriting it.
+	pub fn add(&self, stats: &Stats) -> Result<()> {
+		if stats.is_empty() {
+			return Ok(());
+		}
+		let mut total = Stats::load(&self.path)?;
+		total.merge(stats);
+
+		// Write aside and rename, so a crash never leaves half a file
+		let partial = self.path.with_extension("json.partial");
+		let json = serde_json::to_string(&total).context("Cannot serialize stats")?;
+		fs::write(&partial, json)
+			.with_context(|| format!("Cannot write stats file {}", partial.display()))?;
+		fs::rename(&partial, &self.path)
+			.with_context(|| format!("Cannot write stats file {}", self.path.display()))
+	}
+
+	/// Forgets every press counted in the file.
+	pub fn reset(&self) -> Result<()> {
+		match fs::remove_file(&self.path) {
+			Err(err) if err.kind() != ErrorKind::NotFound => {
+				Err(err).with_context(|| format!("Cannot remove stats file {}", self.path.display()))
+			}
+			_ => Ok(()),
+		}
+	}
+}
+
+/// How long the daemon keeps counts before adding them to the file.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Counts the daemon's presses, adding them to the stats file every
+/// [`FLUSH_INTERVAL`]. Times are on the evdev clock, since the epoch.
+pub struct Recorder {
+	file: StatsLock,
+	bigrams: bool,
+	pending: Stats,
+	/// The key pressed before, for bigrams.
+	previous: Option<String>,
+	flushed: Duration,
+}
+
+impl Recorder {
+	pub fn new(file: StatsLock, bigrams: bool, now: Duration) -> Self {
+		Self {
+			file,
+			bigrams,
+			pending: Stats::default(),
+			previous: None,
+			flushed: now,
+		}
+	}
+
+	pub fn path(&self) -> &Path {
+		self.file.path()
+	}
+
+	/// Counts `key` going down at `time` while `layer` is active.
+	pub fn press(&mut self, key: &str, layer: &str, time: Duration) {
+		self.pending.record(key);
+		*self.pending.layers.entry(layer.to_string()).or_default() += 1;
+		self.pending.hours[(time.as_secs() / 3600 % 24) as usize] += 1;
+		if self.bigrams {
+			if let Some(previous) = self.previous.replace(key.to_string()) {
+				*self
+					.pending
+					.bigrams
+					.entry(format!("{} {}", previous, key))
+					.or_default() += 1;
+			}
+		}
+	}
+
+	/// When counts are next due in the file, `None` with nothing to add.
+	pub fn deadline(&self) -> Option<Duration> {
+		(!self.pending.is_empty()).then(|| self.flushed + FLUSH_INTERVAL)
+	}
+
+	/// Adds the counts so far to the file.
+	pub fn flush(&mut self, now: Duration) -> Result<()> {
+		self.flushed = now;
+		let pending = std::mem::take(&mut self.pending);
+		self.file.add(&pending)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn adds_to_the_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("geekcaps").join("stats.json");
+		let mut session = Stats::default();
+		session.record("A");
+		session.record("A");
+		session.record("Space");
+		let file = StatsLock::wait(&path).unwrap();
+		file.add(&session).unwrap();
+		file.add(&session).unwrap();
+
+		let stats = Stats::load(&path).unwrap();
+		assert_eq!(stats.keys["A"], 4);
+		assert_eq!(stats.total(), 6);
+
+		file.reset().unwrap();
+		assert!(Stats::load(&path).unwrap().is_empty());
+	}
+
+	#[test]
+	fn lets_one_process_write() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("stats.json");
+		let counting = StatsLock::acquire(&path, Duration::ZERO).unwrap();
+		assert!(counting.is_some());
+		assert!(StatsLock::acquire(&path, Duration::from_millis(20))
+			.unwrap()
+			.is_none());
+		assert!(StatsLock::wait(&path)
+			.err()
+			.unwrap()
+			.to_string()
+			.ends_with("most likely by `start` counting presses"));
+		drop(counting);
+		assert!(StatsLock::acquire(&path, Duration::ZERO).unwrap().is_some());
+	}
+
+	#[test]
+	fn records_bigrams_only_when_asked() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("stats.json");
+		let start = Duration::from_secs(3600 * 24 * 365 + 3600 * 9);
+		for bigrams in [false, true] {
+			let file = StatsLock::acquire(&path, Duration::ZERO).unwrap().unwrap();
+			let mut recorder = Recorder::new(file, bigrams, start);
+			assert_eq!(recorder.deadline(), None);
+			recorder.press("T", "base", start);
+			recorder.press("H", "base", start);
+			recorder.press("H", "nav", start + Duration::from_secs(3600));
+			assert_eq!(recorder.deadline(), Some(start + FLUSH_INTERVAL));
+			recorder.flush(start).unwrap();
+			assert_eq!(recorder.deadline(), None);
+		}
+
+		let stats = Stats::load(&path).unwrap();
+		assert_eq!(stats.total(), 6);
+		assert_eq!(stats.layers["nav"], 2);
+		assert_eq!((stats.hours[9], stats.hours[10]), (4, 2));
+		assert_eq!(
+			stats.bigrams,
+			BTreeMap::from([("T H".to_string(), 1), ("H H".to_string(), 1)])
+		);
+		assert_eq!(top(&stats.keys, 1), [("H", 4)]);
+	}
+}